@@ -30,55 +30,693 @@
 
 //        select * from users...              select * from posts                   select * from tags....
 
-pub struct QueryBuilder<'a> {
-    table: &'a str,
-    select: Option<&'a str>,
-    limit: Option<i64>,
-    offset: Option<i64>,
-    order_by: Option<&'a str>,
+use std::fmt;
+use std::marker::PhantomData;
+
+#[macro_use]
+mod condition;
+mod aggregate;
+mod batch;
+mod compound;
+mod dialect;
+mod error;
+mod executor;
+mod fingerprint;
+mod join;
+mod keyset;
+mod lint;
+mod memory;
+mod mutation;
+mod parser;
+mod schema;
+mod select;
+mod window;
+
+pub use aggregate::{avg, count, max, min, sum, Aggregate, AggregateFn};
+pub use batch::{BatchInsert, Chunks};
+pub use compound::{Cte, SetOperator};
+pub use condition::{col, exists, subquery, Column, Condition, Expr, Operator, Value};
+pub use dialect::{Dialect, MySQL, PostgreSQL, SQLite};
+pub use error::BuildError;
+pub use executor::{ExecError, Executor, Rows};
+pub use fingerprint::{fingerprint, Fingerprint};
+pub use join::{table, Join, JoinKind, TableRef};
+pub use keyset::Cursor;
+pub use lint::{Lint, LintWarning, Linter, Rule, Severity};
+pub use memory::MemoryDatabase;
+pub use mutation::{Delete, DeleteBuilder, Insert, InsertBuilder, Update, UpdateBuilder, Upsert};
+pub use parser::{parse, parse_query, ParseError, ParseErrorKind};
+pub use schema::{
+    posts, tags, users, PostQueryBuilder, Table, TagQueryBuilder, TypedColumn, TypedCondition,
+    TypedOrder, TypedQueryBuilder, UserQueryBuilder,
+};
+pub use select::{Direction, OrderBy, Select, SelectItem};
+pub use window::{
+    dense_rank, lag, lead, rank, row_number, window, Over, WindowFn, WindowFunction, WindowSpec,
+};
+
+// Rendered statement: SQL text with placeholders and the values bound to them in order,
+// `params[0]` is the first placeholder (`$1` or `?` depending on the dialect).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub sql: String,
+    pub params: Vec<Value>,
 }
-impl<'a> QueryBuilder<'a> {
-    pub fn new(table: &'a str) -> Self {
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.sql)
+    }
+}
+
+// Identifiers are checked while they are written, the first invalid one is reported by
+// `finish` so every statement gets the same validation without walking it twice.
+struct SqlWriter<'d> {
+    dialect: &'d dyn Dialect,
+    sql: String,
+    params: Vec<Value>,
+    error: Option<BuildError>,
+}
+
+impl<'d> SqlWriter<'d> {
+    fn new(dialect: &'d dyn Dialect) -> Self {
         Self {
-            table,
-            select: None,
-            limit: None,
-            offset: None,
-            order_by: None,
+            dialect,
+            sql: String::new(),
+            params: vec![],
+            error: None,
+        }
+    }
+    fn push(&mut self, sql: &str) {
+        self.sql.push_str(sql);
+    }
+    // `u.id` is quoted part by part, a `*` is kept as is
+    fn push_ident(&mut self, ident: &str) {
+        if !is_valid_identifier(ident) {
+            self.fail(BuildError::InvalidIdentifier(ident.to_string()));
+        }
+        for (i, part) in ident.split('.').enumerate() {
+            if i > 0 {
+                self.sql.push('.');
+            }
+            if part == "*" {
+                self.sql.push('*');
+            } else {
+                self.sql.push_str(&self.dialect.quote_identifier(part));
+            }
+        }
+    }
+    fn push_ident_list(&mut self, idents: &[&str]) {
+        for (i, ident) in idents.iter().enumerate() {
+            if i > 0 {
+                self.push(", ");
+            }
+            self.push_ident(ident);
+        }
+    }
+    // keep the first error, rendering goes on so nested parts need no early return
+    fn fail(&mut self, error: BuildError) {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+    fn push_bool(&mut self, value: bool) {
+        self.sql.push_str(self.dialect.boolean(value));
+    }
+    fn push_param(&mut self, value: Value) {
+        self.params.push(value);
+        let placeholder = self.dialect.placeholder(self.params.len());
+        self.sql.push_str(&placeholder);
+    }
+    fn finish(self) -> Result<Query, BuildError> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(Query {
+                sql: self.sql,
+                params: self.params,
+            }),
         }
     }
+}
+
+// Convenience layer over `Select`: the methods take borrowed strings and the builder copies
+// them into the owned query, `to_ast` hands that query out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryBuilder<'a> {
+    query: Select,
+    // reported by `build`, the query itself cannot hold it
+    error: Option<BuildError>,
+    // the condition of the last `keyset` call, ANDed to WHERE when the query is taken
+    after: Option<Condition>,
+    borrowed: PhantomData<&'a str>,
+}
+impl<'a> QueryBuilder<'a> {
+    pub fn new(name: &'a str) -> Self {
+        Self::from(Select::new(table(name)))
+    }
+    pub fn alias(&mut self, alias: &'a str) -> &mut Self {
+        self.query.from.alias = Some(alias.to_string());
+        self
+    }
+    pub fn join(
+        &mut self,
+        kind: JoinKind,
+        table: impl Into<TableRef>,
+        on: Option<Condition>,
+    ) -> &mut Self {
+        self.query.joins.push(Join {
+            kind,
+            table: table.into(),
+            on,
+        });
+        self
+    }
+    pub fn inner_join(&mut self, table: impl Into<TableRef>, on: Condition) -> &mut Self {
+        self.join(JoinKind::Inner, table, Some(on))
+    }
+    pub fn left_join(&mut self, table: impl Into<TableRef>, on: Condition) -> &mut Self {
+        self.join(JoinKind::Left, table, Some(on))
+    }
+    pub fn right_join(&mut self, table: impl Into<TableRef>, on: Condition) -> &mut Self {
+        self.join(JoinKind::Right, table, Some(on))
+    }
+    pub fn full_join(&mut self, table: impl Into<TableRef>, on: Condition) -> &mut Self {
+        self.join(JoinKind::Full, table, Some(on))
+    }
+    pub fn cross_join(&mut self, table: impl Into<TableRef>) -> &mut Self {
+        self.join(JoinKind::Cross, table, None)
+    }
     pub fn limit(&mut self, limit: i64) -> &mut Self {
-        self.limit = Some(limit);
+        self.query.limit = Some(limit);
         self
     }
     pub fn offset(&mut self, offset: i64) -> &mut Self {
-        self.offset = Some(offset);
+        self.query.offset = Some(offset);
         self
     }
+    // Comma separated `column [ASC|DESC]` items, replaces the previous ordering.
     pub fn order_by(&mut self, order_by: &'a str) -> &mut Self {
-        self.order_by = Some(order_by);
+        self.query.order_by = split_list(order_by)
+            .into_iter()
+            .map(parse_order_by)
+            .collect();
+        self
+    }
+    // Keyset pagination: ORDER BY `order_by` LIMIT `page_size`, starting after the row the
+    // cursor was taken from. Without a cursor this is the first page. Each call replaces
+    // the previous page, so one builder can fetch page after page.
+    pub fn keyset(
+        &mut self,
+        order_by: &'a str,
+        after: Option<&Cursor>,
+        page_size: i64,
+    ) -> &mut Self {
+        self.order_by(order_by).limit(page_size);
+        self.after = None;
+        if self.query.order_by.is_empty() {
+            self.error = Some(BuildError::MissingKeysetOrder);
+            return self;
+        }
+        self.error = None;
+        self.after = after.map(|cursor| keyset::after(&self.query.order_by, cursor.values()));
         self
     }
+    // Comma separated columns, replaces the previously selected columns but keeps the
+    // aggregates.
     pub fn select(&mut self, select: &'a str) -> &mut Self {
-        self.select = Some(select);
+        self.query
+            .items
+            .retain(|item| !matches!(item, SelectItem::Column(_)));
+        let columns = split_list(select)
+            .into_iter()
+            .map(|column| SelectItem::Column(column.to_string()));
+        self.query.items.splice(0..0, columns);
         self
     }
-    pub fn build(&self) -> String {
-        let mut query_str = String::from("");
-        query_str = format!(
-            "SELECT {} from {}",
-            query_str + self.select.unwrap_or("*"),
-            self.table
-        );
-        if self.limit.is_some() {
-            query_str = format!("{} LIMIT {}", query_str, self.limit.unwrap());
+    pub fn aggregate(&mut self, aggregate: Aggregate) -> &mut Self {
+        self.query.items.push(SelectItem::Aggregate(aggregate));
+        self
+    }
+    pub fn window(&mut self, function: WindowFunction) -> &mut Self {
+        self.query.items.push(SelectItem::Window(function));
+        self
+    }
+    // WINDOW name AS (spec), for functions defined `.over(name)`
+    pub fn named_window(&mut self, name: &'a str, spec: WindowSpec) -> &mut Self {
+        self.query.windows.push((name.to_string(), spec));
+        self
+    }
+    // PostgreSQL only, comma separated like `select`. ORDER BY has to start with the same
+    // columns, the first row of each group in that order is kept.
+    pub fn distinct_on(&mut self, columns: &'a str) -> &mut Self {
+        self.query.distinct_on = split_list(columns).into_iter().map(String::from).collect();
+        self
+    }
+    // Calling filter several times ANDs the conditions together.
+    pub fn filter(&mut self, condition: Condition) -> &mut Self {
+        and_condition(&mut self.query.filter, condition);
+        self
+    }
+    // Comma separated like `select`, calling it several times appends to the list.
+    pub fn group_by(&mut self, group_by: &'a str) -> &mut Self {
+        self.query
+            .group_by
+            .extend(split_list(group_by).into_iter().map(String::from));
+        self
+    }
+    pub fn having(&mut self, condition: Condition) -> &mut Self {
+        and_condition(&mut self.query.having, condition);
+        self
+    }
+    // WITH name AS (query)
+    pub fn with(&mut self, name: &'a str, query: impl Into<Select>) -> &mut Self {
+        self.query.ctes.push(Cte {
+            name: name.to_string(),
+            query: query.into(),
+            recursive: false,
+        });
+        self
+    }
+    // WITH RECURSIVE name AS (query)
+    pub fn with_recursive(&mut self, name: &'a str, query: impl Into<Select>) -> &mut Self {
+        self.query.ctes.push(Cte {
+            name: name.to_string(),
+            query: query.into(),
+            recursive: true,
+        });
+        self
+    }
+    // ORDER BY, LIMIT and OFFSET of this query apply to the combined result, the other
+    // query must not have its own.
+    pub fn set_operation(&mut self, operator: SetOperator, query: impl Into<Select>) -> &mut Self {
+        self.query.set_operations.push((operator, query.into()));
+        self
+    }
+    pub fn union(&mut self, query: impl Into<Select>) -> &mut Self {
+        self.set_operation(SetOperator::Union, query)
+    }
+    pub fn union_all(&mut self, query: impl Into<Select>) -> &mut Self {
+        self.set_operation(SetOperator::UnionAll, query)
+    }
+    pub fn intersect(&mut self, query: impl Into<Select>) -> &mut Self {
+        self.set_operation(SetOperator::Intersect, query)
+    }
+    pub fn except(&mut self, query: impl Into<Select>) -> &mut Self {
+        self.set_operation(SetOperator::Except, query)
+    }
+
+    // Owned copy of the query built so far, a failed `keyset` is only reported by `build`.
+    pub fn to_ast(&self) -> Select {
+        let mut query = self.query.clone();
+        if let Some(after) = &self.after {
+            and_condition(&mut query.filter, after.clone());
+        }
+        query
+    }
+    pub fn build(&self) -> Result<Query, BuildError> {
+        self.build_with(&PostgreSQL)
+    }
+    pub fn build_with(&self, dialect: &dyn Dialect) -> Result<Query, BuildError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        self.to_ast().render(dialect)
+    }
+    // Runs the query, e.g. against a `MemoryDatabase` in tests.
+    pub fn fetch(&self, executor: &mut dyn Executor) -> Result<Rows, ExecError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone().into());
+        }
+        executor.fetch(&self.to_ast())
+    }
+}
+
+impl From<Select> for QueryBuilder<'_> {
+    fn from(query: Select) -> Self {
+        Self {
+            query,
+            error: None,
+            after: None,
+            borrowed: PhantomData,
+        }
+    }
+}
+impl From<&QueryBuilder<'_>> for Select {
+    fn from(builder: &QueryBuilder<'_>) -> Self {
+        builder.to_ast()
+    }
+}
+impl From<&mut QueryBuilder<'_>> for Select {
+    fn from(builder: &mut QueryBuilder<'_>) -> Self {
+        builder.to_ast()
+    }
+}
+
+fn and_condition(target: &mut Option<Condition>, condition: Condition) {
+    *target = Some(match target.take() {
+        Some(existing) => existing.and(condition),
+        None => condition,
+    });
+}
+
+// `created_at DESC` -> `created_at` descending, the direction is case insensitive.
+// An item that does not parse is kept whole as column and rejected by `Select::validate`.
+fn parse_order_by(item: &str) -> OrderBy {
+    let invalid = || OrderBy {
+        column: item.to_string(),
+        direction: None,
+    };
+    let words: Vec<&str> = item.split_whitespace().collect();
+    let direction = match words.get(1) {
+        None => None,
+        Some(word) if word.eq_ignore_ascii_case("asc") => Some(Direction::Asc),
+        Some(word) if word.eq_ignore_ascii_case("desc") => Some(Direction::Desc),
+        Some(_) => return invalid(),
+    };
+    match words.as_slice() {
+        [column] | [column, _] => OrderBy {
+            column: column.to_string(),
+            direction,
+        },
+        _ => invalid(),
+    }
+}
+
+fn write_order_by(order_by: &[OrderBy], w: &mut SqlWriter<'_>) {
+    for (i, order) in order_by.iter().enumerate() {
+        if i > 0 {
+            w.push(", ");
+        }
+        w.push_ident(&order.column);
+        if let Some(direction) = order.direction {
+            w.push(" ");
+            w.push(direction.as_str());
         }
-        if self.offset.is_some() {
-            query_str = format!("{} OFFSET {}", query_str, self.offset.unwrap());
+    }
+}
+
+// A plain SQL identifier, optionally qualified (`u.id`); `*` is accepted as last part.
+fn is_valid_identifier(ident: &str) -> bool {
+    let parts: Vec<&str> = ident.split('.').collect();
+    parts.iter().enumerate().all(|(i, part)| {
+        if *part == "*" {
+            return i == parts.len() - 1;
         }
-        if self.order_by.is_some() {
-            query_str = format!("{} order by {}", query_str, self.order_by.unwrap());
+        let mut chars = part.chars();
+        match chars.next() {
+            Some(first) if first.is_alphabetic() || first == '_' => {
+                chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+            }
+            _ => false,
         }
-        query_str
+    })
+}
+
+fn split_list(list: &str) -> Vec<&str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn where_values_are_bound_as_params() {
+        let query = QueryBuilder::new("users")
+            .select("id,username")
+            .filter(col("age").gt(18))
+            .filter(col("username").like("%'; DROP TABLE users; --"))
+            .limit(10)
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "id", "username" FROM "users" WHERE "age" > $1 AND "username" LIKE $2 LIMIT 10"#
+        );
+        assert_eq!(
+            query.params,
+            vec![Value::Int(18), "%'; DROP TABLE users; --".into()]
+        );
+    }
+
+    #[test]
+    fn predicates_combine_with_and_or_not() {
+        let query = QueryBuilder::new("users")
+            .filter(
+                col("role")
+                    .is_in(["admin", "owner"])
+                    .or(col("age").between(18, 30).and(col("banned_at").is_null()))
+                    .and(!col("email").like("%@spam.com").or(col("id").ne(1))),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT * FROM "users" WHERE ("role" IN ($1, $2) OR ("age" BETWEEN $3 AND $4 AND "banned_at" IS NULL)) AND NOT ("email" LIKE $5 OR "id" <> $6)"#
+        );
+        assert_eq!(query.params.len(), 6);
+        assert_eq!(query.params[5], Value::Int(1));
+    }
+
+    #[test]
+    fn empty_in_list_never_matches() {
+        let ids: Vec<i64> = vec![];
+        let query = QueryBuilder::new("users")
+            .filter(col("id").is_in(ids))
+            .build()
+            .unwrap();
+        assert_eq!(query.sql, r#"SELECT * FROM "users" WHERE FALSE"#);
+        assert!(query.params.is_empty());
+    }
+
+    #[test]
+    fn joins_with_aliases_and_qualified_columns() {
+        let query = QueryBuilder::new("users")
+            .alias("u")
+            .select("u.id,p.title,t.name")
+            .inner_join(table("posts").alias("p"), col("p.user_id").eq(col("u.id")))
+            .left_join(
+                table("tags").alias("t"),
+                col("t.post_id")
+                    .eq(col("p.id"))
+                    .and(col("t.name").ne("draft")),
+            )
+            .cross_join("settings")
+            .filter(col("u.active").eq(true))
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "u"."id", "p"."title", "t"."name" FROM "users" AS "u" INNER JOIN "posts" AS "p" ON "p"."user_id" = "u"."id" LEFT JOIN "tags" AS "t" ON "t"."post_id" = "p"."id" AND "t"."name" <> $1 CROSS JOIN "settings" WHERE "u"."active" = $2"#
+        );
+        assert_eq!(query.params, vec!["draft".into(), Value::Bool(true)]);
+    }
+
+    #[test]
+    fn aggregates_with_group_by_and_having() {
+        let query = QueryBuilder::new("orders")
+            .select("customer_id, status")
+            .aggregate(count("*").alias("orders"))
+            .aggregate(sum("amount").alias("total"))
+            .aggregate(max("created_at"))
+            .filter(col("status").ne("cancelled"))
+            .group_by("customer_id,status")
+            .having(sum("amount").gt(100).and(count("*").ge(2)))
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "customer_id", "status", COUNT(*) AS "orders", SUM("amount") AS "total", MAX("created_at") FROM "orders" WHERE "status" <> $1 GROUP BY "customer_id", "status" HAVING SUM("amount") > $2 AND COUNT(*) >= $3"#
+        );
+        assert_eq!(
+            query.params,
+            vec!["cancelled".into(), Value::Int(100), Value::Int(2)]
+        );
+
+        let query = QueryBuilder::new("orders")
+            .aggregate(avg("amount"))
+            .aggregate(min("amount"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT AVG("amount"), MIN("amount") FROM "orders""#
+        );
+    }
+
+    #[test]
+    fn ungrouped_columns_are_rejected() {
+        let err = QueryBuilder::new("orders")
+            .select("customer_id,status")
+            .aggregate(count("*"))
+            .group_by("customer_id")
+            .build()
+            .unwrap_err();
+        assert_eq!(err, BuildError::UngroupedColumn("status".into()));
+
+        let err = QueryBuilder::new("orders")
+            .group_by("customer_id")
+            .build()
+            .unwrap_err();
+        assert_eq!(err, BuildError::UngroupedColumn("*".into()));
+    }
+
+    #[test]
+    fn clauses_are_emitted_in_sql_order() {
+        let query = QueryBuilder::new("user")
+            .order_by("created_at DESC, id")
+            .offset(5)
+            .limit(10)
+            .select("id,username,display_name")
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "id", "username", "display_name" FROM "user" ORDER BY "created_at" DESC, "id" LIMIT 10 OFFSET 5"#
+        );
+    }
+
+    #[test]
+    fn invalid_input_is_rejected() {
+        let build = |query: &mut QueryBuilder| query.build().unwrap_err();
+        assert_eq!(
+            build(QueryBuilder::new("user").limit(-1)),
+            BuildError::NegativeLimit(-1)
+        );
+        assert_eq!(
+            build(QueryBuilder::new("user").offset(-5)),
+            BuildError::NegativeOffset(-5)
+        );
+        assert_eq!(
+            build(&mut QueryBuilder::new(" ")),
+            BuildError::EmptyTableName
+        );
+        assert_eq!(
+            build(QueryBuilder::new("user").select("id; DROP TABLE user")),
+            BuildError::InvalidIdentifier("id; DROP TABLE user".into())
+        );
+        assert_eq!(
+            build(QueryBuilder::new("user").filter(col("1=1 OR id").eq(1))),
+            BuildError::InvalidIdentifier("1=1 OR id".into())
+        );
+        assert_eq!(
+            build(QueryBuilder::new("user").order_by("created_at DESC NULLS")),
+            BuildError::InvalidOrderBy("created_at DESC NULLS".into())
+        );
+        assert_eq!(
+            build(QueryBuilder::new("user").order_by("created_at sideways")),
+            BuildError::InvalidOrderBy("created_at sideways".into())
+        );
+        assert_eq!(
+            build(QueryBuilder::new("user").filter(exists(QueryBuilder::new("posts").limit(-1)))),
+            BuildError::NegativeLimit(-1)
+        );
+        assert_eq!(
+            DeleteBuilder::new("").build(),
+            Err(BuildError::EmptyTableName)
+        );
+        assert!(QueryBuilder::new("user").select("u.*").build().is_ok());
+    }
+
+    #[test]
+    fn subqueries_number_params_in_text_order() {
+        let active = QueryBuilder::new("orders")
+            .select("user_id")
+            .filter(col("status").eq("paid"))
+            .clone();
+        let query = QueryBuilder::new("users")
+            .select("id")
+            .filter(col("age").gt(18))
+            .filter(col("id").in_query(&active))
+            .filter(exists(
+                QueryBuilder::new("bans")
+                    .filter(col("bans.user_id").eq(col("users.id")))
+                    .filter(col("bans.reason").ne("spam")),
+            ))
+            .filter(col("score").gt(subquery(
+                QueryBuilder::new("scores").aggregate(avg("score")),
+            )))
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "id" FROM "users" WHERE "age" > $1 AND "id" IN (SELECT "user_id" FROM "orders" WHERE "status" = $2) AND EXISTS (SELECT * FROM "bans" WHERE "bans"."user_id" = "users"."id" AND "bans"."reason" <> $3) AND "score" > (SELECT AVG("score") FROM "scores")"#
+        );
+        assert_eq!(query.params, vec![18.into(), "paid".into(), "spam".into()]);
+    }
+
+    #[test]
+    fn ctes_and_set_operations() {
+        let recent = QueryBuilder::new("posts")
+            .filter(col("created_at").gt(1_700_000_000))
+            .clone();
+        let query = QueryBuilder::new("recent")
+            .with("recent", &recent)
+            .select("id")
+            .filter(col("author_id").eq(7))
+            .union_all(
+                QueryBuilder::new("archived")
+                    .select("id")
+                    .filter(col("author_id").eq(7)),
+            )
+            .order_by("id DESC")
+            .limit(10)
+            .build_with(&MySQL)
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            "WITH `recent` AS (SELECT * FROM `posts` WHERE `created_at` > ?) SELECT `id` FROM `recent` WHERE `author_id` = ? UNION ALL SELECT `id` FROM `archived` WHERE `author_id` = ? ORDER BY `id` DESC LIMIT 10"
+        );
+        assert_eq!(query.params, vec![1_700_000_000.into(), 7.into(), 7.into()]);
+
+        let tree = QueryBuilder::new("categories")
+            .select("id,parent_id")
+            .filter(col("id").eq(1))
+            .union_all(
+                QueryBuilder::new("categories")
+                    .alias("c")
+                    .select("c.id,c.parent_id")
+                    .inner_join(table("tree").alias("t"), col("c.parent_id").eq(col("t.id"))),
+            )
+            .clone();
+        let query = QueryBuilder::new("tree")
+            .with_recursive("tree", &tree)
+            .select("id")
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"WITH RECURSIVE "tree" AS (SELECT "id", "parent_id" FROM "categories" WHERE "id" = $1 UNION ALL SELECT "c"."id", "c"."parent_id" FROM "categories" AS "c" INNER JOIN "tree" AS "t" ON "c"."parent_id" = "t"."id") SELECT "id" FROM "tree""#
+        );
+
+        let err = QueryBuilder::new("a")
+            .intersect(QueryBuilder::new("b").limit(1))
+            .build()
+            .unwrap_err();
+        assert_eq!(err, BuildError::InvalidSetOperand("LIMIT/OFFSET"));
+        // operands are checked like standalone queries
+        let err = QueryBuilder::new("a")
+            .select("id")
+            .union(QueryBuilder::new("b").select("id").aggregate(count("*")))
+            .build()
+            .unwrap_err();
+        assert_eq!(err, BuildError::UngroupedColumn("id".to_string()));
+        let err = QueryBuilder::new("a")
+            .except(&QueryBuilder::new(" "))
+            .build()
+            .unwrap_err();
+        assert_eq!(err, BuildError::EmptyTableName);
+        let query = QueryBuilder::new("a")
+            .except(&QueryBuilder::new("b"))
+            .union(&QueryBuilder::new("c"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT * FROM "a" EXCEPT SELECT * FROM "b" UNION SELECT * FROM "c""#
+        );
     }
 }
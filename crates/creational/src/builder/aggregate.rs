@@ -0,0 +1,79 @@
+// Aggregate select expressions: `count("*").alias("total")`, `sum("amount")`...
+// An aggregate can also be compared in a HAVING clause: `count("*").gt(5)`.
+use super::{Condition, Expr, Operator, Select, SqlWriter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFn {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFn {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            AggregateFn::Count => "COUNT",
+            AggregateFn::Sum => "SUM",
+            AggregateFn::Avg => "AVG",
+            AggregateFn::Min => "MIN",
+            AggregateFn::Max => "MAX",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Aggregate {
+    pub func: AggregateFn,
+    pub column: String,
+    pub alias: Option<String>,
+}
+
+pub fn count(column: &str) -> Aggregate {
+    Aggregate::new(AggregateFn::Count, column)
+}
+pub fn sum(column: &str) -> Aggregate {
+    Aggregate::new(AggregateFn::Sum, column)
+}
+pub fn avg(column: &str) -> Aggregate {
+    Aggregate::new(AggregateFn::Avg, column)
+}
+pub fn min(column: &str) -> Aggregate {
+    Aggregate::new(AggregateFn::Min, column)
+}
+pub fn max(column: &str) -> Aggregate {
+    Aggregate::new(AggregateFn::Max, column)
+}
+
+impl Aggregate {
+    pub fn new(func: AggregateFn, column: &str) -> Self {
+        Self {
+            func,
+            column: column.to_string(),
+            alias: None,
+        }
+    }
+    pub fn alias(self, alias: &str) -> Self {
+        Self {
+            alias: Some(alias.to_string()),
+            ..self
+        }
+    }
+
+    predicate_methods!();
+
+    pub(super) fn write(&self, w: &mut SqlWriter<'_>) {
+        Expr::from(self.clone()).write(w);
+        if let Some(alias) = &self.alias {
+            w.push(" AS ");
+            w.push_ident(alias);
+        }
+    }
+}
+
+impl From<Aggregate> for Expr {
+    fn from(aggregate: Aggregate) -> Self {
+        Expr::Aggregate(aggregate.func, aggregate.column)
+    }
+}
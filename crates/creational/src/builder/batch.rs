@@ -0,0 +1,270 @@
+// Bulk loads: rows from an iterator are packed into as few multi-row INSERTs as the
+// driver accepts. A statement ends before it would exceed the parameter limit or the
+// maximum statement size:
+//
+//   rows --chunks(max_params, max_statement_size)--> INSERT ... VALUES (..), (..)
+//                                                     INSERT ... VALUES (..), (..)
+//                                                     ...
+//
+// Rows are only pulled from the iterator when the next statement is requested, so a
+// large import never holds more than one statement in memory.
+use std::iter::Peekable;
+use std::marker::PhantomData;
+
+use super::{BuildError, Dialect, Insert, Query, Upsert, Value};
+
+// 65535 bind parameters is the limit of the PostgreSQL and MySQL protocols, the 1 MiB
+// statement stays well below MySQL's default `max_allowed_packet`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchInsert<'a> {
+    template: Insert,
+    max_params: usize,
+    max_statement_size: usize,
+    borrowed: PhantomData<&'a str>,
+}
+
+impl<'a> BatchInsert<'a> {
+    pub fn new(table: &'a str) -> Self {
+        Self {
+            template: Insert::new(table),
+            max_params: 65_535,
+            max_statement_size: 1 << 20,
+            borrowed: PhantomData,
+        }
+    }
+    // Comma separated like `InsertBuilder::columns`.
+    pub fn columns(&mut self, columns: &'a str) -> &mut Self {
+        self.template.columns = super::mutation::strings(columns);
+        self
+    }
+    // Same conflict handling as `InsertBuilder::on_conflict` and `do_update`, applied to
+    // every statement of the batch.
+    pub fn on_conflict(&mut self, columns: &'a str) -> &mut Self {
+        self.upsert().conflict = super::mutation::strings(columns);
+        self
+    }
+    pub fn do_update(&mut self, columns: &'a str) -> &mut Self {
+        self.upsert().update = super::mutation::strings(columns);
+        self
+    }
+    fn upsert(&mut self) -> &mut Upsert {
+        self.template.upsert.get_or_insert_with(|| Upsert {
+            conflict: vec![],
+            update: vec![],
+        })
+    }
+    // SQLite before 3.32 only binds 999 parameters.
+    pub fn max_params(&mut self, max_params: usize) -> &mut Self {
+        self.max_params = max_params;
+        self
+    }
+    // In bytes of SQL text, the bound values are not counted.
+    pub fn max_statement_size(&mut self, max_statement_size: usize) -> &mut Self {
+        self.max_statement_size = max_statement_size;
+        self
+    }
+
+    // One statement per item. An empty iterator yields no statement, an error ends the
+    // batch: the rows before it were yielded, the rows after it are not read.
+    pub fn chunks<'d, I>(&self, rows: I, dialect: &'d dyn Dialect) -> Chunks<'d, I::IntoIter>
+    where
+        I: IntoIterator<Item = Vec<Value>>,
+    {
+        Chunks {
+            template: self.template.clone(),
+            max_params: self.max_params,
+            max_statement_size: self.max_statement_size,
+            rows: rows.into_iter().peekable(),
+            dialect,
+            overhead: None,
+            done: false,
+        }
+    }
+}
+
+pub struct Chunks<'d, I: Iterator<Item = Vec<Value>>> {
+    template: Insert,
+    max_params: usize,
+    max_statement_size: usize,
+    rows: Peekable<I>,
+    dialect: &'d dyn Dialect,
+    // length of the statement without any VALUES row
+    overhead: Option<usize>,
+    done: bool,
+}
+
+impl<I: Iterator<Item = Vec<Value>>> Chunks<'_, I> {
+    // `(p1, p2, ...)` for the row whose first parameter has number `first`.
+    fn row_size(&self, first: usize) -> usize {
+        let columns = self.template.columns.len();
+        let placeholders: usize = (first..first + columns)
+            .map(|index| self.dialect.placeholder(index).len())
+            .sum();
+        placeholders + ", ".len() * (columns - 1) + "()".len()
+    }
+
+    // Rendered once with a single row of NULLs, which also validates the template.
+    fn overhead(&mut self) -> Result<usize, BuildError> {
+        if let Some(overhead) = self.overhead {
+            return Ok(overhead);
+        }
+        let mut statement = self.template.clone();
+        statement.rows = vec![vec![Value::Null; statement.columns.len()]];
+        let overhead = statement.render(self.dialect)?.sql.len() - self.row_size(1);
+        self.overhead = Some(overhead);
+        Ok(overhead)
+    }
+
+    fn next_statement(&mut self) -> Result<Option<Query>, BuildError> {
+        if self.rows.peek().is_none() {
+            return Ok(None);
+        }
+        let mut size = self.overhead()?;
+        let columns = self.template.columns.len();
+        let mut rows: Vec<Vec<Value>> = vec![];
+        while let Some(row) = self.rows.peek() {
+            if row.len() != columns {
+                // the rows before it still go out
+                if !rows.is_empty() {
+                    break;
+                }
+                return Err(BuildError::ColumnCountMismatch {
+                    expected: columns,
+                    found: row.len(),
+                });
+            }
+            let separator = if rows.is_empty() { 0 } else { ", ".len() };
+            let row_size = separator + self.row_size(rows.len() * columns + 1);
+            let params = (rows.len() + 1) * columns;
+            if params > self.max_params || size + row_size > self.max_statement_size {
+                if rows.is_empty() {
+                    return Err(BuildError::RowTooLarge {
+                        params,
+                        size: size + row_size,
+                    });
+                }
+                break;
+            }
+            size += row_size;
+            rows.extend(self.rows.next());
+        }
+        let statement = Insert {
+            rows,
+            ..self.template.clone()
+        };
+        statement.render(self.dialect).map(Some)
+    }
+}
+
+impl<I: Iterator<Item = Vec<Value>>> Iterator for Chunks<'_, I> {
+    type Item = Result<Query, BuildError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let statement = self.next_statement();
+        self.done = statement.is_err();
+        statement.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    fn rows(count: i64) -> impl Iterator<Item = Vec<Value>> {
+        (1..=count).map(|id| vec![id.into(), format!("user {}", id).into()])
+    }
+
+    #[test]
+    fn splits_on_the_parameter_limit() {
+        let mut batch = BatchInsert::new("users");
+        batch.columns("id, name").max_params(5);
+        let queries: Vec<Query> = batch
+            .chunks(rows(5), &PostgreSQL)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let sql: Vec<&str> = queries.iter().map(|query| query.sql.as_str()).collect();
+        assert_eq!(
+            sql,
+            [
+                r#"INSERT INTO "users" ("id", "name") VALUES ($1, $2), ($3, $4)"#,
+                r#"INSERT INTO "users" ("id", "name") VALUES ($1, $2), ($3, $4)"#,
+                r#"INSERT INTO "users" ("id", "name") VALUES ($1, $2)"#,
+            ]
+        );
+        assert_eq!(queries[2].params, vec![5.into(), "user 5".into()]);
+        assert_eq!(batch.chunks(rows(0), &PostgreSQL).count(), 0);
+    }
+
+    #[test]
+    fn splits_on_the_statement_size() {
+        let mut batch = BatchInsert::new("events");
+        batch
+            .columns("id, name")
+            .on_conflict("id")
+            .do_update("name")
+            .max_statement_size(150);
+        for dialect in [&PostgreSQL as &dyn Dialect, &MySQL, &SQLite] {
+            let queries: Vec<Query> = batch
+                .chunks(rows(40), dialect)
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert!(queries.len() > 1);
+            assert!(queries.iter().all(|query| query.sql.len() <= 150));
+            // every statement but the last is full: one more row would not fit
+            let mut inserted = 0;
+            for query in &queries[..queries.len() - 1] {
+                inserted += query.params.len() / 2;
+                let mut bigger = InsertBuilder::new("events");
+                bigger
+                    .columns("id, name")
+                    .on_conflict("id")
+                    .do_update("name");
+                for row in rows(inserted as i64 + 1).skip(inserted - query.params.len() / 2) {
+                    bigger.values(row);
+                }
+                assert!(bigger.build_with(dialect).unwrap().sql.len() > 150);
+            }
+            let params: usize = queries.iter().map(|query| query.params.len()).sum();
+            assert_eq!(params, 80);
+        }
+    }
+
+    #[test]
+    fn reports_rows_that_never_fit() {
+        let mut batch = BatchInsert::new("users");
+        batch.columns("id, name").max_params(1);
+        let mut chunks = batch.chunks(rows(3), &PostgreSQL);
+        assert_eq!(
+            chunks.next(),
+            Some(Err(BuildError::RowTooLarge {
+                params: 2,
+                size: 50
+            }))
+        );
+        assert_eq!(chunks.next(), None);
+
+        let mut batch = BatchInsert::new("users");
+        batch.columns("id, name").max_params(4);
+        let mixed = vec![
+            vec![1.into(), "ann".into()],
+            vec![2.into(), "bob".into()],
+            vec![3.into()],
+        ];
+        let results: Vec<_> = batch.chunks(mixed, &MySQL).collect();
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1..],
+            [Err(BuildError::ColumnCountMismatch {
+                expected: 2,
+                found: 1
+            })]
+        );
+        let no_columns = BatchInsert::new("users")
+            .chunks(rows(1), &PostgreSQL)
+            .collect::<Vec<_>>();
+        assert_eq!(no_columns, [Err(BuildError::EmptyInsert)]);
+    }
+}
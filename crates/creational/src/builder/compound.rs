@@ -0,0 +1,32 @@
+// Queries made of other queries: common table expressions and set operations.
+// Subqueries in conditions live in `condition.rs` (`in_query`, `exists`, `subquery`).
+// Every part is rendered into the same writer, so placeholders keep counting across the
+// nested queries and the params stay in text order.
+use super::Select;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cte {
+    pub name: String,
+    pub query: Select,
+    // a recursive CTE may reference its own name, usually as second UNION ALL operand
+    pub recursive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperator {
+    Union,
+    UnionAll,
+    Intersect,
+    Except,
+}
+
+impl SetOperator {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            SetOperator::Union => "UNION",
+            SetOperator::UnionAll => "UNION ALL",
+            SetOperator::Intersect => "INTERSECT",
+            SetOperator::Except => "EXCEPT",
+        }
+    }
+}
@@ -0,0 +1,329 @@
+// Typed predicates for the WHERE clause.
+// Every value supplied by the caller ends up as a bound parameter, the SQL text only ever
+// contains column names, operators and placeholders.
+use std::ops::Not;
+
+use super::{AggregateFn, BuildError, Select, SqlWriter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Int(value.into())
+    }
+}
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+// One side of a comparison: either a column of the queried table or a bound value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Column(String),
+    Param(Value),
+    Aggregate(AggregateFn, String),
+    // scalar subquery, must return a single column and at most one row
+    Subquery(Box<Select>),
+}
+
+impl<T: Into<Value>> From<T> for Expr {
+    fn from(value: T) -> Self {
+        Expr::Param(value.into())
+    }
+}
+impl From<Column> for Expr {
+    fn from(column: Column) -> Self {
+        Expr::Column(column.0)
+    }
+}
+
+impl Expr {
+    pub(super) fn write(&self, w: &mut SqlWriter<'_>) {
+        match self {
+            Expr::Column(name) => w.push_ident(name),
+            Expr::Param(value) => w.push_param(value.clone()),
+            Expr::Aggregate(func, column) => {
+                w.push(func.as_str());
+                w.push("(");
+                w.push_ident(column);
+                w.push(")");
+            }
+            Expr::Subquery(query) => {
+                w.push("(");
+                query.write(w);
+                w.push(")");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Operator {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Eq => "=",
+            Operator::Ne => "<>",
+            Operator::Lt => "<",
+            Operator::Le => "<=",
+            Operator::Gt => ">",
+            Operator::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Compare(Expr, Operator, Expr),
+    In(Expr, Vec<Expr>),
+    InQuery(Expr, Box<Select>),
+    Exists(Box<Select>),
+    Like(Expr, Expr),
+    IsNull(Expr),
+    Between(Expr, Expr, Expr),
+    // row value comparison: (a, b) > ($1, $2)
+    RowCompare(Vec<Expr>, Operator, Vec<Expr>),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    pub fn and(self, other: Condition) -> Condition {
+        match self {
+            Condition::And(mut conditions) => {
+                conditions.push(other);
+                Condition::And(conditions)
+            }
+            condition => Condition::And(vec![condition, other]),
+        }
+    }
+    pub fn or(self, other: Condition) -> Condition {
+        match self {
+            Condition::Or(mut conditions) => {
+                conditions.push(other);
+                Condition::Or(conditions)
+            }
+            condition => Condition::Or(vec![condition, other]),
+        }
+    }
+
+    pub(super) fn write(&self, w: &mut SqlWriter<'_>) {
+        match self {
+            Condition::Compare(left, op, right) => {
+                left.write(w);
+                w.push(" ");
+                w.push(op.as_str());
+                w.push(" ");
+                right.write(w);
+            }
+            // an empty IN list can never match, `x IN ()` is not valid SQL
+            Condition::In(_, values) if values.is_empty() => w.push_bool(false),
+            Condition::In(expr, values) => {
+                expr.write(w);
+                w.push(" IN (");
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        w.push(", ");
+                    }
+                    value.write(w);
+                }
+                w.push(")");
+            }
+            Condition::InQuery(expr, query) => {
+                expr.write(w);
+                w.push(" IN (");
+                query.write(w);
+                w.push(")");
+            }
+            Condition::Exists(query) => {
+                w.push("EXISTS (");
+                query.write(w);
+                w.push(")");
+            }
+            Condition::Like(expr, pattern) => {
+                expr.write(w);
+                w.push(" LIKE ");
+                pattern.write(w);
+            }
+            Condition::IsNull(expr) => {
+                expr.write(w);
+                w.push(" IS NULL");
+            }
+            Condition::Between(expr, low, high) => {
+                expr.write(w);
+                w.push(" BETWEEN ");
+                low.write(w);
+                w.push(" AND ");
+                high.write(w);
+            }
+            Condition::RowCompare(left, op, right) => {
+                if left.len() != right.len() {
+                    w.fail(BuildError::RowLengthMismatch {
+                        left: left.len(),
+                        right: right.len(),
+                    });
+                }
+                Self::write_row(left, w);
+                w.push(" ");
+                w.push(op.as_str());
+                w.push(" ");
+                Self::write_row(right, w);
+            }
+            Condition::And(conditions) => Self::write_list(conditions, " AND ", true, w),
+            Condition::Or(conditions) => Self::write_list(conditions, " OR ", false, w),
+            Condition::Not(condition) => {
+                w.push("NOT (");
+                condition.write(w);
+                w.push(")");
+            }
+        }
+    }
+
+    fn write_row(exprs: &[Expr], w: &mut SqlWriter<'_>) {
+        w.push("(");
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                w.push(", ");
+            }
+            expr.write(w);
+        }
+        w.push(")");
+    }
+
+    fn write_list(conditions: &[Condition], separator: &str, empty: bool, w: &mut SqlWriter<'_>) {
+        if conditions.is_empty() {
+            w.push_bool(empty);
+            return;
+        }
+        for (i, condition) in conditions.iter().enumerate() {
+            if i > 0 {
+                w.push(separator);
+            }
+            let nested = matches!(condition, Condition::And(_) | Condition::Or(_));
+            if nested {
+                w.push("(");
+            }
+            condition.write(w);
+            if nested {
+                w.push(")");
+            }
+        }
+    }
+}
+
+impl Not for Condition {
+    type Output = Condition;
+    fn not(self) -> Self::Output {
+        match self {
+            Condition::Not(condition) => *condition,
+            condition => Condition::Not(Box::new(condition)),
+        }
+    }
+}
+
+// Predicate methods shared by everything that can stand on the left side of a condition,
+// the implementing type only needs to be `Into<Expr>`.
+macro_rules! predicate_methods {
+    () => {
+        pub fn eq(self, value: impl Into<Expr>) -> Condition {
+            Condition::Compare(self.into(), Operator::Eq, value.into())
+        }
+        pub fn ne(self, value: impl Into<Expr>) -> Condition {
+            Condition::Compare(self.into(), Operator::Ne, value.into())
+        }
+        pub fn lt(self, value: impl Into<Expr>) -> Condition {
+            Condition::Compare(self.into(), Operator::Lt, value.into())
+        }
+        pub fn le(self, value: impl Into<Expr>) -> Condition {
+            Condition::Compare(self.into(), Operator::Le, value.into())
+        }
+        pub fn gt(self, value: impl Into<Expr>) -> Condition {
+            Condition::Compare(self.into(), Operator::Gt, value.into())
+        }
+        pub fn ge(self, value: impl Into<Expr>) -> Condition {
+            Condition::Compare(self.into(), Operator::Ge, value.into())
+        }
+        pub fn is_in<V: Into<Expr>>(self, values: impl IntoIterator<Item = V>) -> Condition {
+            Condition::In(self.into(), values.into_iter().map(Into::into).collect())
+        }
+        pub fn in_query(self, query: impl Into<Select>) -> Condition {
+            Condition::InQuery(self.into(), Box::new(query.into()))
+        }
+        pub fn like(self, pattern: impl Into<Expr>) -> Condition {
+            Condition::Like(self.into(), pattern.into())
+        }
+        pub fn is_null(self) -> Condition {
+            Condition::IsNull(self.into())
+        }
+        pub fn is_not_null(self) -> Condition {
+            !self.is_null()
+        }
+        pub fn between(self, low: impl Into<Expr>, high: impl Into<Expr>) -> Condition {
+            Condition::Between(self.into(), low.into(), high.into())
+        }
+    };
+}
+
+// Subqueries accept a `Select` or a `&QueryBuilder`.
+pub fn exists(query: impl Into<Select>) -> Condition {
+    Condition::Exists(Box::new(query.into()))
+}
+
+pub fn subquery(query: impl Into<Select>) -> Expr {
+    Expr::Subquery(Box::new(query.into()))
+}
+
+// Entry point of the predicate DSL: `col("age").gt(18).and(col("name").like("A%"))`
+#[derive(Debug, Clone)]
+pub struct Column(String);
+
+pub fn col(name: &str) -> Column {
+    Column(name.to_string())
+}
+
+impl Column {
+    predicate_methods!();
+}
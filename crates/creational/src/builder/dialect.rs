@@ -0,0 +1,293 @@
+// Every backend speaks a slightly different SQL, the dialect decides how identifiers are
+// quoted, how placeholders look, how LIMIT/OFFSET are spelled and how booleans are written.
+// The same QueryBuilder renders for any of them: `query.build_with(&MySQL)`.
+
+pub trait Dialect {
+    fn name(&self) -> &'static str;
+    // quote a single identifier part, `users.id` is quoted part by part by the writer
+    fn quote_identifier(&self, ident: &str) -> String;
+    // `index` starts at 1
+    fn placeholder(&self, index: usize) -> String;
+    fn boolean(&self, value: bool) -> &'static str;
+    fn limit_offset(&self, limit: Option<i64>, offset: Option<i64>) -> String;
+
+    fn supports_returning(&self) -> bool {
+        true
+    }
+    // MySQL since 8.0 and SQLite since 3.25, a dialect for older servers returns false
+    fn supports_window_functions(&self) -> bool {
+        true
+    }
+    fn supports_distinct_on(&self) -> bool {
+        false
+    }
+    // SQLite since 3.39
+    fn supports_full_join(&self) -> bool {
+        true
+    }
+    // Conflict handling of an upsert, an empty `update` list means keep the existing row.
+    fn upsert(&self, conflict: &[&str], update: &[&str]) -> String {
+        let quote = |columns: &[&str]| {
+            columns
+                .iter()
+                .map(|column| self.quote_identifier(column))
+                .collect::<Vec<_>>()
+        };
+        let mut sql = format!(" ON CONFLICT ({})", quote(conflict).join(", "));
+        if update.is_empty() {
+            sql.push_str(" DO NOTHING");
+            return sql;
+        }
+        let assignments: Vec<String> = quote(update)
+            .iter()
+            .map(|column| format!("{} = EXCLUDED.{}", column, column))
+            .collect();
+        sql.push_str(&format!(" DO UPDATE SET {}", assignments.join(", ")));
+        sql
+    }
+}
+
+fn quote_with(ident: &str, quote: char) -> String {
+    let escaped = ident.replace(quote, &format!("{}{}", quote, quote));
+    format!("{}{}{}", quote, escaped, quote)
+}
+
+pub struct PostgreSQL;
+impl Dialect for PostgreSQL {
+    fn name(&self) -> &'static str {
+        "PostgreSQL"
+    }
+    fn quote_identifier(&self, ident: &str) -> String {
+        quote_with(ident, '"')
+    }
+    fn placeholder(&self, index: usize) -> String {
+        format!("${}", index)
+    }
+    fn boolean(&self, value: bool) -> &'static str {
+        if value {
+            "TRUE"
+        } else {
+            "FALSE"
+        }
+    }
+    fn limit_offset(&self, limit: Option<i64>, offset: Option<i64>) -> String {
+        let mut sql = String::new();
+        if let Some(limit) = limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        if let Some(offset) = offset {
+            sql.push_str(&format!(" OFFSET {}", offset));
+        }
+        sql
+    }
+    fn supports_distinct_on(&self) -> bool {
+        true
+    }
+}
+
+pub struct MySQL;
+impl Dialect for MySQL {
+    fn name(&self) -> &'static str {
+        "MySQL"
+    }
+    fn quote_identifier(&self, ident: &str) -> String {
+        quote_with(ident, '`')
+    }
+    fn placeholder(&self, _index: usize) -> String {
+        "?".into()
+    }
+    fn boolean(&self, value: bool) -> &'static str {
+        if value {
+            "TRUE"
+        } else {
+            "FALSE"
+        }
+    }
+    // MySQL has no standalone OFFSET, the documented way to skip rows without a bound is
+    // the largest BIGINT UNSIGNED as row count.
+    fn limit_offset(&self, limit: Option<i64>, offset: Option<i64>) -> String {
+        match (limit, offset) {
+            (Some(limit), Some(offset)) => format!(" LIMIT {}, {}", offset, limit),
+            (Some(limit), None) => format!(" LIMIT {}", limit),
+            (None, Some(offset)) => format!(" LIMIT {}, 18446744073709551615", offset),
+            (None, None) => String::new(),
+        }
+    }
+    fn supports_returning(&self) -> bool {
+        false
+    }
+    // only LEFT and RIGHT outer joins
+    fn supports_full_join(&self) -> bool {
+        false
+    }
+    // MySQL picks the conflicting unique key itself, a no-op assignment keeps the row.
+    // The inserted row is named by a row alias, which needs MySQL 8.0.19 or later and
+    // replaces the deprecated `VALUES(column)`.
+    fn upsert(&self, conflict: &[&str], update: &[&str]) -> String {
+        if update.is_empty() {
+            let assignments: Vec<String> = conflict
+                .iter()
+                .take(1)
+                .map(|column| self.quote_identifier(column))
+                .map(|column| format!("{} = {}", column, column))
+                .collect();
+            return format!(" ON DUPLICATE KEY UPDATE {}", assignments.join(", "));
+        }
+        let alias = self.quote_identifier("new");
+        let assignments: Vec<String> = update
+            .iter()
+            .map(|column| self.quote_identifier(column))
+            .map(|column| format!("{} = {}.{}", column, alias, column))
+            .collect();
+        format!(
+            " AS {} ON DUPLICATE KEY UPDATE {}",
+            alias,
+            assignments.join(", ")
+        )
+    }
+}
+
+pub struct SQLite;
+impl Dialect for SQLite {
+    fn name(&self) -> &'static str {
+        "SQLite"
+    }
+    fn quote_identifier(&self, ident: &str) -> String {
+        quote_with(ident, '"')
+    }
+    fn placeholder(&self, _index: usize) -> String {
+        "?".into()
+    }
+    // SQLite stores booleans as integers
+    fn boolean(&self, value: bool) -> &'static str {
+        if value {
+            "1"
+        } else {
+            "0"
+        }
+    }
+    // OFFSET is only valid after a LIMIT, a negative limit means no upper bound
+    fn limit_offset(&self, limit: Option<i64>, offset: Option<i64>) -> String {
+        match (limit, offset) {
+            (Some(limit), Some(offset)) => format!(" LIMIT {} OFFSET {}", limit, offset),
+            (Some(limit), None) => format!(" LIMIT {}", limit),
+            (None, Some(offset)) => format!(" LIMIT -1 OFFSET {}", offset),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    // Golden output of the same queries for every supported backend.
+    fn report() -> QueryBuilder<'static> {
+        let mut query = QueryBuilder::new("orders");
+        query
+            .alias("o")
+            .select("u.name")
+            .aggregate(count("*").alias("orders"))
+            .inner_join(table("users").alias("u"), col("u.id").eq(col("o.user_id")))
+            .filter(col("o.status").is_in(["paid", "shipped"]))
+            .group_by("u.name")
+            .having(sum("o.amount").gt(100))
+            .limit(10)
+            .order_by("u.name asc")
+            .offset(20);
+        query
+    }
+
+    fn paging() -> QueryBuilder<'static> {
+        let mut query = QueryBuilder::new("user");
+        query
+            .select("id,display_name")
+            .filter(
+                col("id")
+                    .is_in(Vec::<i64>::new())
+                    .or(col("banned").is_null()),
+            )
+            .offset(5);
+        query
+    }
+
+    #[test]
+    fn postgres_golden() {
+        let query = report().build_with(&PostgreSQL).unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "u"."name", COUNT(*) AS "orders" FROM "orders" AS "o" INNER JOIN "users" AS "u" ON "u"."id" = "o"."user_id" WHERE "o"."status" IN ($1, $2) GROUP BY "u"."name" HAVING SUM("o"."amount") > $3 ORDER BY "u"."name" ASC LIMIT 10 OFFSET 20"#
+        );
+        assert_eq!(
+            query.params,
+            vec!["paid".into(), "shipped".into(), 100.into()]
+        );
+
+        let query = paging().build_with(&PostgreSQL).unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "id", "display_name" FROM "user" WHERE FALSE OR "banned" IS NULL OFFSET 5"#
+        );
+        // build() renders PostgreSQL
+        assert_eq!(paging().build().unwrap(), query);
+    }
+
+    #[test]
+    fn mysql_golden() {
+        let query = report().build_with(&MySQL).unwrap();
+        assert_eq!(
+            query.sql,
+            "SELECT `u`.`name`, COUNT(*) AS `orders` FROM `orders` AS `o` INNER JOIN `users` AS `u` ON `u`.`id` = `o`.`user_id` WHERE `o`.`status` IN (?, ?) GROUP BY `u`.`name` HAVING SUM(`o`.`amount`) > ? ORDER BY `u`.`name` ASC LIMIT 20, 10"
+        );
+        assert_eq!(
+            query.params,
+            vec!["paid".into(), "shipped".into(), 100.into()]
+        );
+
+        let query = paging().build_with(&MySQL).unwrap();
+        assert_eq!(
+            query.sql,
+            "SELECT `id`, `display_name` FROM `user` WHERE FALSE OR `banned` IS NULL LIMIT 5, 18446744073709551615"
+        );
+    }
+
+    #[test]
+    fn sqlite_golden() {
+        let query = report().build_with(&SQLite).unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "u"."name", COUNT(*) AS "orders" FROM "orders" AS "o" INNER JOIN "users" AS "u" ON "u"."id" = "o"."user_id" WHERE "o"."status" IN (?, ?) GROUP BY "u"."name" HAVING SUM("o"."amount") > ? ORDER BY "u"."name" ASC LIMIT 10 OFFSET 20"#
+        );
+
+        let query = paging().build_with(&SQLite).unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "id", "display_name" FROM "user" WHERE 0 OR "banned" IS NULL LIMIT -1 OFFSET 5"#
+        );
+    }
+
+    #[test]
+    fn full_join_is_rejected_by_mysql() {
+        let mut query = QueryBuilder::new("users");
+        query
+            .alias("u")
+            .full_join(table("posts").alias("p"), col("p.user_id").eq(col("u.id")));
+        assert_eq!(
+            query.build_with(&MySQL),
+            Err(BuildError::Unsupported {
+                dialect: "MySQL",
+                feature: "FULL JOIN",
+            })
+        );
+        assert_eq!(
+            query.build_with(&SQLite).unwrap().sql,
+            r#"SELECT * FROM "users" AS "u" FULL JOIN "posts" AS "p" ON "p"."user_id" = "u"."id""#
+        );
+    }
+
+    #[test]
+    fn quotes_inside_identifiers_are_escaped() {
+        assert_eq!(PostgreSQL.quote_identifier(r#"we"ird"#), r#""we""ird""#);
+        assert_eq!(MySQL.quote_identifier("we`ird"), "`we``ird`");
+    }
+}
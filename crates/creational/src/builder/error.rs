@@ -0,0 +1,125 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    // a selected column is neither aggregated nor listed in GROUP BY
+    UngroupedColumn(String),
+    EmptyTableName,
+    // not a plain, optionally qualified, identifier: `id`, `u.id`, `u.*`
+    InvalidIdentifier(String),
+    // an ORDER BY item other than `column [ASC|DESC]`
+    InvalidOrderBy(String),
+    NegativeLimit(i64),
+    NegativeOffset(i64),
+    // a UNION/INTERSECT/EXCEPT operand carries a clause that belongs to the whole statement
+    InvalidSetOperand(&'static str),
+    // both sides of a row comparison must have the same number of values
+    RowLengthMismatch {
+        left: usize,
+        right: usize,
+    },
+    // a pagination cursor token that was not produced by `Cursor::encode`
+    InvalidCursor,
+    // keyset pagination without any ORDER BY column to page on
+    MissingKeysetOrder,
+    // the statement uses a feature the target dialect cannot express
+    Unsupported {
+        dialect: &'static str,
+        feature: &'static str,
+    },
+    // INSERT without columns or without any row
+    EmptyInsert,
+    // a VALUES row does not have one value per inserted column
+    ColumnCountMismatch {
+        expected: usize,
+        found: usize,
+    },
+    // UPDATE without any SET assignment
+    EmptyUpdate,
+    // DO UPDATE needs the unique columns the conflict is detected on
+    MissingConflictTarget,
+    // OVER "w" without a WINDOW "w" definition
+    UnknownWindow(String),
+    // an ORDER BY item before the DISTINCT ON columns
+    DistinctOnOrderBy(String),
+    // LAG/LEAD counts rows away from the current one, backwards is the other function
+    NegativeWindowOffset(i64),
+    // a single row of a batch insert is over its max parameters or max statement size
+    RowTooLarge {
+        params: usize,
+        size: usize,
+    },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::UngroupedColumn(column) => write!(
+                f,
+                "column `{}` must appear in GROUP BY or be used in an aggregate",
+                column
+            ),
+            BuildError::EmptyTableName => write!(f, "table name must not be empty"),
+            BuildError::InvalidIdentifier(ident) => {
+                write!(f, "`{}` is not a valid identifier", ident)
+            }
+            BuildError::InvalidOrderBy(item) => write!(
+                f,
+                "`{}` is not a valid ORDER BY item, expected `column [ASC|DESC]`",
+                item
+            ),
+            BuildError::NegativeLimit(limit) => {
+                write!(f, "LIMIT must not be negative, got {}", limit)
+            }
+            BuildError::NegativeOffset(offset) => {
+                write!(f, "OFFSET must not be negative, got {}", offset)
+            }
+            BuildError::InvalidSetOperand(clause) => write!(
+                f,
+                "{} is not allowed in a set operation operand, put it on the outer query",
+                clause
+            ),
+            BuildError::RowLengthMismatch { left, right } => write!(
+                f,
+                "row comparison between {} columns and {} values",
+                left, right
+            ),
+            BuildError::InvalidCursor => write!(f, "invalid pagination cursor"),
+            BuildError::MissingKeysetOrder => {
+                write!(
+                    f,
+                    "keyset pagination needs an ORDER BY ending in a unique column"
+                )
+            }
+            BuildError::Unsupported { dialect, feature } => {
+                write!(f, "{} is not supported by {}", feature, dialect)
+            }
+            BuildError::EmptyInsert => write!(f, "INSERT needs at least one column and one row"),
+            BuildError::ColumnCountMismatch { expected, found } => write!(
+                f,
+                "INSERT row has {} values but {} columns are inserted",
+                found, expected
+            ),
+            BuildError::EmptyUpdate => write!(f, "UPDATE needs at least one SET assignment"),
+            BuildError::MissingConflictTarget => {
+                write!(f, "upsert needs the conflict columns, call on_conflict")
+            }
+            BuildError::UnknownWindow(name) => write!(f, "window `{}` is not defined", name),
+            BuildError::DistinctOnOrderBy(column) => write!(
+                f,
+                "ORDER BY must start with the DISTINCT ON columns, found `{}`",
+                column
+            ),
+            BuildError::NegativeWindowOffset(offset) => {
+                write!(f, "LAG/LEAD offset must not be negative, got {}", offset)
+            }
+            BuildError::RowTooLarge { params, size } => write!(
+                f,
+                "a single row needs {} parameters and a {} byte statement, over the batch limits",
+                params, size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -0,0 +1,104 @@
+// Running a query instead of only rendering it.
+// The `Executor` trait is the seam between query construction and storage: the in-memory
+// engine in `memory.rs` evaluates the `Select` AST directly, a database driver renders it
+// for its dialect and sends `sql` and `params` to the server. Code written against
+// `&mut dyn Executor` runs unchanged on both.
+//
+//   QueryBuilder --to_ast()--> Select --fetch()--> dyn Executor --> Rows
+//                                                    ├── MemoryDatabase (evaluates the AST)
+//                                                    └── driver (render(dialect) + send)
+use std::fmt;
+
+use super::{BuildError, Select, Value};
+
+pub trait Executor {
+    fn fetch(&mut self, query: &Select) -> Result<Rows, ExecError>;
+}
+
+// Result set: output column names and one value per column in every row.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Rows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Rows {
+    pub fn new(columns: &[&str]) -> Self {
+        Self {
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            rows: vec![],
+        }
+    }
+
+    // Values of one output column, top to bottom.
+    pub fn column(&self, name: &str) -> Option<Vec<&Value>> {
+        let index = self.columns.iter().position(|column| column == name)?;
+        Some(self.rows.iter().map(|row| &row[index]).collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecError {
+    // the query would not even render
+    Build(BuildError),
+    UnknownTable(String),
+    UnknownColumn(String),
+    // an unqualified column exists in more than one joined table
+    AmbiguousColumn(String),
+    // two values that cannot be compared or added, e.g. a number and a text
+    TypeMismatch(Value, Value),
+    // integers whose sum is out of the i64 range
+    Overflow(Value, Value),
+    // a row or a set operation operand with the wrong number of columns
+    ColumnCountMismatch { expected: usize, found: usize },
+    // IN (subquery) and scalar subqueries must return a single column
+    SubqueryColumns(usize),
+    // a scalar subquery returned more than one row
+    SubqueryRows(usize),
+    // COUNT, SUM... used where no rows are grouped, e.g. in WHERE
+    MisplacedAggregate,
+    // the executor cannot evaluate this part of the query
+    Unsupported(&'static str),
+}
+
+impl From<BuildError> for ExecError {
+    fn from(error: BuildError) -> Self {
+        ExecError::Build(error)
+    }
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::Build(error) => write!(f, "{}", error),
+            ExecError::UnknownTable(name) => write!(f, "table `{}` does not exist", name),
+            ExecError::UnknownColumn(name) => write!(f, "column `{}` does not exist", name),
+            ExecError::AmbiguousColumn(name) => write!(f, "column `{}` is ambiguous", name),
+            ExecError::TypeMismatch(left, right) => {
+                write!(f, "incompatible values {:?} and {:?}", left, right)
+            }
+            ExecError::Overflow(left, right) => {
+                write!(f, "adding {:?} and {:?} overflows", left, right)
+            }
+            ExecError::ColumnCountMismatch { expected, found } => {
+                write!(f, "expected {} columns, found {}", expected, found)
+            }
+            ExecError::SubqueryColumns(columns) => write!(
+                f,
+                "subquery must return a single column, returned {}",
+                columns
+            ),
+            ExecError::SubqueryRows(rows) => write!(
+                f,
+                "scalar subquery must return at most one row, returned {}",
+                rows
+            ),
+            ExecError::MisplacedAggregate => {
+                write!(f, "aggregates are only allowed in SELECT and HAVING")
+            }
+            ExecError::Unsupported(feature) => write!(f, "{} is not supported", feature),
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
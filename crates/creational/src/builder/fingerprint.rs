@@ -0,0 +1,248 @@
+// Query shapes for logging: two queries that only differ in their values, limits, IN-list
+// lengths, identifier quoting or whitespace get the same fingerprint.
+//
+//   SELECT "id" FROM "users" WHERE "age" > $1 AND "id" IN ($2, $3) LIMIT 10
+//   select id from users where age > 40 and id in (7, 8, 9) limit 50
+//     --> SELECT "id" FROM "users" WHERE "age" > ? AND "id" IN (...) LIMIT ?
+//
+// The hash is FNV-1a over that normalized text, so it is the same on every run and
+// platform, unlike `std::collections::hash_map::DefaultHasher`.
+use std::fmt;
+
+use super::parser::{tokenize, Token, RESERVED};
+use super::{AggregateFn, Query};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    // the normalized SQL the hash was computed from
+    pub shape: String,
+    pub hash: u64,
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.hash)
+    }
+}
+
+impl Query {
+    pub fn fingerprint(&self) -> Fingerprint {
+        fingerprint(&self.sql)
+    }
+}
+
+// Also works for hand-written SQL with inline literals and for INSERT/UPDATE/DELETE.
+pub fn fingerprint(sql: &str) -> Fingerprint {
+    let shape = match tokenize(sql) {
+        Ok(tokens) => shape(&tokens),
+        // text the tokenizer does not understand is only whitespace-normalized
+        Err(_) => sql.split_whitespace().collect::<Vec<_>>().join(" "),
+    };
+    Fingerprint {
+        hash: fnv1a(shape.as_bytes()),
+        shape,
+    }
+}
+
+// Words of the mutation statements, on top of the ones the SELECT parser knows.
+const KEYWORDS: [&str; 14] = [
+    "CONFLICT",
+    "DELETE",
+    "DO",
+    "DUPLICATE",
+    "EXCLUDED",
+    "INSERT",
+    "INTO",
+    "KEY",
+    "NOTHING",
+    "RETURNING",
+    "SET",
+    "UPDATE",
+    "VALUES",
+    "WITH",
+];
+
+fn is_keyword(word: &str) -> bool {
+    RESERVED
+        .iter()
+        .chain(KEYWORDS.iter())
+        .any(|keyword| keyword.eq_ignore_ascii_case(word))
+        || is_function(word)
+}
+
+fn is_function(word: &str) -> bool {
+    ["ROW_NUMBER", "RANK", "DENSE_RANK", "LAG", "LEAD"]
+        .iter()
+        .any(|func| func.eq_ignore_ascii_case(word))
+        || [
+            AggregateFn::Count,
+            AggregateFn::Sum,
+            AggregateFn::Avg,
+            AggregateFn::Min,
+            AggregateFn::Max,
+        ]
+        .iter()
+        .any(|func| func.as_str().eq_ignore_ascii_case(word))
+}
+
+fn shape(tokens: &[(usize, Token)]) -> String {
+    let mut pieces: Vec<String> = vec![];
+    for (_, token) in tokens {
+        let piece = match token {
+            Token::Text(_) | Token::Number(_) | Token::Placeholder(_) => "?".to_string(),
+            Token::Word(word)
+                if word.eq_ignore_ascii_case("TRUE") || word.eq_ignore_ascii_case("FALSE") =>
+            {
+                "?".to_string()
+            }
+            Token::Word(word) if is_keyword(word) => word.to_uppercase(),
+            // unquoted identifiers are case-insensitive, quoting them changes nothing else
+            Token::Word(word) => format!("\"{}\"", word.to_lowercase()),
+            Token::Quoted(ident) => format!("\"{}\"", ident),
+            Token::Symbol(";") => continue,
+            Token::Symbol("!=") => "<>".to_string(),
+            Token::Symbol(symbol) => symbol.to_string(),
+        };
+        if piece == "?" && is_sign(&pieces) {
+            pieces.pop();
+        }
+        if piece == ")" && collapse_in_list(&mut pieces) {
+            continue;
+        }
+        pieces.push(piece);
+    }
+    let mut shape = String::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        if i > 0 && needs_space(&pieces[i - 1], piece) {
+            shape.push(' ');
+        }
+        shape.push_str(piece);
+    }
+    shape
+}
+
+// A `-` that does not follow a value is the sign of the next literal.
+fn is_sign(pieces: &[String]) -> bool {
+    match pieces {
+        [.., before, minus] if minus == "-" => {
+            !(before == "?" || before == ")" || before.starts_with('"'))
+        }
+        _ => false,
+    }
+}
+
+// `IN (?, ?, ?)` becomes `IN (...)` whatever the number of values, returns true when the
+// closing parenthesis was pushed already.
+fn collapse_in_list(pieces: &mut Vec<String>) -> bool {
+    let Some(open) = pieces.iter().rposition(|piece| piece == "(") else {
+        return false;
+    };
+    let is_list = open > 0
+        && pieces[open - 1] == "IN"
+        && open + 1 < pieces.len()
+        && pieces[open + 1..]
+            .iter()
+            .all(|piece| piece == "?" || piece == ",");
+    if is_list {
+        pieces.truncate(open + 1);
+        pieces.push("...".to_string());
+        pieces.push(")".to_string());
+    }
+    is_list
+}
+
+fn needs_space(previous: &str, next: &str) -> bool {
+    let glued = matches!(next, "," | ")" | ".")
+        || matches!(previous, "(" | ".")
+        || (next == "(" && is_function(previous));
+    !glued
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn values_limits_and_in_lists_do_not_change_the_fingerprint() {
+        let build = |ids: &[i64], age: i64, limit: i64| {
+            QueryBuilder::new("users")
+                .select("id, u.name")
+                .filter(col("age").gt(age).and(col("id").is_in(ids.to_vec())))
+                .aggregate(count("*"))
+                .group_by("id, u.name")
+                .limit(limit)
+                .build()
+                .unwrap()
+        };
+        let first = build(&[1], 18, 10).fingerprint();
+        assert_eq!(
+            first.shape,
+            r#"SELECT "id", "u"."name", COUNT(*) FROM "users" WHERE "age" > ? AND "id" IN (...) GROUP BY "id", "u"."name" LIMIT ?"#
+        );
+        assert_eq!(build(&[4, 5, 6], -3, 500).fingerprint(), first);
+
+        let hand_written = "select id, u.name, count(*)\n  from users\n where age > -40 and id in (7, 8)\n group by id, u.name limit 5;";
+        assert_eq!(fingerprint(hand_written), first);
+        assert_eq!(first.to_string(), format!("{:016x}", first.hash));
+    }
+
+    #[test]
+    fn different_shapes_get_different_fingerprints() {
+        let by_age = QueryBuilder::new("users")
+            .filter(col("age").gt(18))
+            .build()
+            .unwrap();
+        let by_name = QueryBuilder::new("users")
+            .filter(col("name").gt(18))
+            .build()
+            .unwrap();
+        assert_ne!(by_age.fingerprint(), by_name.fingerprint());
+        assert_ne!(by_age.fingerprint().hash, by_name.fingerprint().hash);
+
+        // identifiers keep their quotes: "Users" and users are different tables
+        assert_ne!(
+            fingerprint(r#"SELECT * FROM "Users""#),
+            fingerprint("SELECT * FROM Users")
+        );
+        assert_eq!(
+            fingerprint("SELECT * FROM Users"),
+            fingerprint(r#"SELECT * FROM "users""#)
+        );
+    }
+
+    #[test]
+    fn mutations_and_dialects() {
+        let insert = |name: &str| {
+            InsertBuilder::new("users")
+                .columns("name")
+                .values(vec![name.into()])
+                .build_with(&MySQL)
+                .unwrap()
+        };
+        assert_eq!(insert("ann").fingerprint(), insert("bob").fingerprint());
+        assert_eq!(
+            insert("ann").fingerprint().shape,
+            r#"INSERT INTO "users" ("name") VALUES (?)"#
+        );
+
+        let query = QueryBuilder::new("users")
+            .filter(col("id").is_in([1, 2]))
+            .to_ast();
+        assert_eq!(
+            query.render(&PostgreSQL).unwrap().fingerprint(),
+            query.render(&SQLite).unwrap().fingerprint()
+        );
+        // the hash is FNV-1a, stable across runs and builds
+        assert_eq!(fingerprint("").hash, 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fingerprint("SELECT").hash, fingerprint("  select  ").hash);
+    }
+}
@@ -0,0 +1,87 @@
+// Tables taking part in a query, optionally aliased, and the joins between them.
+// Columns of a joined table are referenced with their qualified name: `col("p.user_id")`.
+use super::{BuildError, Condition, SqlWriter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRef {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+pub fn table(name: &str) -> TableRef {
+    TableRef {
+        name: name.to_string(),
+        alias: None,
+    }
+}
+
+impl TableRef {
+    pub fn alias(self, alias: &str) -> Self {
+        Self {
+            alias: Some(alias.to_string()),
+            ..self
+        }
+    }
+
+    pub(super) fn write(&self, w: &mut SqlWriter<'_>) {
+        w.push_ident(&self.name);
+        if let Some(alias) = &self.alias {
+            w.push(" AS ");
+            w.push_ident(alias);
+        }
+    }
+}
+
+impl From<&str> for TableRef {
+    fn from(name: &str) -> Self {
+        table(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    Full,
+    Cross,
+}
+
+impl JoinKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JoinKind::Inner => "INNER JOIN",
+            JoinKind::Left => "LEFT JOIN",
+            JoinKind::Right => "RIGHT JOIN",
+            JoinKind::Full => "FULL JOIN",
+            JoinKind::Cross => "CROSS JOIN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub table: TableRef,
+    // always None for a cross join
+    pub on: Option<Condition>,
+}
+
+impl Join {
+    pub(super) fn write(&self, w: &mut SqlWriter<'_>) {
+        if self.kind == JoinKind::Full && !w.dialect.supports_full_join() {
+            w.fail(BuildError::Unsupported {
+                dialect: w.dialect.name(),
+                feature: "FULL JOIN",
+            });
+        }
+        w.push(" ");
+        w.push(self.kind.as_str());
+        w.push(" ");
+        self.table.write(w);
+        if let Some(on) = &self.on {
+            w.push(" ON ");
+            on.write(w);
+        }
+    }
+}
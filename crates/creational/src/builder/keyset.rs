@@ -0,0 +1,304 @@
+// Keyset (cursor) pagination.
+// Instead of skipping rows with OFFSET, the next page starts after the sort key of the last
+// row seen: `WHERE (created_at, id) < ($1, $2) ORDER BY created_at DESC, id DESC LIMIT 20`.
+// The sort key must be unique (end it with the primary key) and must not contain NULLs.
+use super::{BuildError, Condition, Direction, Expr, Operator, OrderBy, Value};
+
+// Sort key values of the last row of a page, handed to API clients as an opaque token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    values: Vec<Value>,
+}
+
+impl Cursor {
+    pub fn new(values: impl IntoIterator<Item = Value>) -> Self {
+        Self {
+            values: values.into_iter().collect(),
+        }
+    }
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    // Length prefixed values, base64url encoded so the token is URL safe.
+    pub fn encode(&self) -> String {
+        let mut raw = String::new();
+        for value in &self.values {
+            match value {
+                Value::Null => raw.push('n'),
+                Value::Bool(value) => raw.push_str(if *value { "t" } else { "f" }),
+                Value::Int(value) => raw.push_str(&format!("i{};", value)),
+                Value::Float(value) => raw.push_str(&format!("d{:x};", value.to_bits())),
+                Value::Text(text) => raw.push_str(&format!("s{}:{}", text.len(), text)),
+            }
+        }
+        base64_encode(raw.as_bytes())
+    }
+
+    pub fn decode(token: &str) -> Result<Self, BuildError> {
+        let raw = base64_decode(token).ok_or(BuildError::InvalidCursor)?;
+        let raw = String::from_utf8(raw).map_err(|_| BuildError::InvalidCursor)?;
+        let mut values = vec![];
+        let mut rest = raw.as_str();
+        while let Some(tag) = rest.chars().next() {
+            rest = &rest[tag.len_utf8()..];
+            let value = match tag {
+                'n' => Value::Null,
+                't' => Value::Bool(true),
+                'f' => Value::Bool(false),
+                'i' => {
+                    let (number, tail) = rest.split_once(';').ok_or(BuildError::InvalidCursor)?;
+                    rest = tail;
+                    Value::Int(number.parse().map_err(|_| BuildError::InvalidCursor)?)
+                }
+                'd' => {
+                    let (bits, tail) = rest.split_once(';').ok_or(BuildError::InvalidCursor)?;
+                    rest = tail;
+                    let bits =
+                        u64::from_str_radix(bits, 16).map_err(|_| BuildError::InvalidCursor)?;
+                    Value::Float(f64::from_bits(bits))
+                }
+                's' => {
+                    let (len, tail) = rest.split_once(':').ok_or(BuildError::InvalidCursor)?;
+                    let len: usize = len.parse().map_err(|_| BuildError::InvalidCursor)?;
+                    let text = tail.get(..len).ok_or(BuildError::InvalidCursor)?;
+                    rest = &tail[len..];
+                    Value::Text(text.to_string())
+                }
+                _ => return Err(BuildError::InvalidCursor),
+            };
+            values.push(value);
+        }
+        Ok(Self { values })
+    }
+}
+
+// Rows strictly after `values` in the `order` sort order. A uniform direction becomes a
+// single row value comparison, mixed directions are expanded column by column:
+// a > $1 OR (a = $1 AND b < $2)
+pub(super) fn after(order: &[OrderBy], values: &[Value]) -> Condition {
+    let operator = |direction: Option<Direction>| match direction {
+        Some(Direction::Desc) => Operator::Lt,
+        _ => Operator::Gt,
+    };
+    let columns: Vec<Expr> = order
+        .iter()
+        .map(|order| Expr::Column(order.column.clone()))
+        .collect();
+    let values: Vec<Expr> = values.iter().cloned().map(Expr::Param).collect();
+    let uniform = order
+        .windows(2)
+        .all(|pair| operator(pair[0].direction) == operator(pair[1].direction));
+    if columns.len() == 1 && values.len() == 1 {
+        let direction = operator(order[0].direction);
+        return Condition::Compare(columns[0].clone(), direction, values[0].clone());
+    }
+    if uniform || columns.len() != values.len() {
+        // a length mismatch is reported by the row comparison when rendering
+        let direction = operator(order.first().and_then(|order| order.direction));
+        return Condition::RowCompare(columns, direction, values);
+    }
+    let branches = (0..columns.len())
+        .map(|i| {
+            let mut branch: Vec<Condition> = (0..i)
+                .map(|j| Condition::Compare(columns[j].clone(), Operator::Eq, values[j].clone()))
+                .collect();
+            branch.push(Condition::Compare(
+                columns[i].clone(),
+                operator(order[i].direction),
+                values[i].clone(),
+            ));
+            if branch.len() == 1 {
+                branch.remove(0)
+            } else {
+                Condition::And(branch)
+            }
+        })
+        .collect();
+    Condition::Or(branches)
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let sextets: Vec<u32> = encoded
+        .bytes()
+        .map(|c| BASE64.iter().position(|b| *b == c).map(|i| i as u32))
+        .collect::<Option<_>>()?;
+    let mut bytes = vec![];
+    for chunk in sextets.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, sextet)| n | sextet << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((n >> (16 - 8 * i) & 0xff) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::Cursor;
+
+    #[test]
+    fn first_and_next_page() {
+        let first = QueryBuilder::new("posts")
+            .select("id,title,created_at")
+            .filter(col("published").eq(true))
+            .keyset("created_at, id", None, 20)
+            .build()
+            .unwrap();
+        assert_eq!(
+            first.sql,
+            r#"SELECT "id", "title", "created_at" FROM "posts" WHERE "published" = $1 ORDER BY "created_at", "id" LIMIT 20"#
+        );
+
+        let token = Cursor::new([1_700_000_000.into(), 42.into()]).encode();
+        let cursor = Cursor::decode(&token).unwrap();
+        let next = QueryBuilder::new("posts")
+            .select("id,title,created_at")
+            .filter(col("published").eq(true))
+            .keyset("created_at, id", Some(&cursor), 20)
+            .build()
+            .unwrap();
+        assert_eq!(
+            next.sql,
+            r#"SELECT "id", "title", "created_at" FROM "posts" WHERE "published" = $1 AND ("created_at", "id") > ($2, $3) ORDER BY "created_at", "id" LIMIT 20"#
+        );
+        assert_eq!(
+            next.params,
+            vec![true.into(), 1_700_000_000.into(), 42.into()]
+        );
+    }
+
+    #[test]
+    fn each_keyset_call_replaces_the_previous_page() {
+        let mut posts = QueryBuilder::new("posts");
+        posts.filter(col("published").eq(true));
+        for id in [20, 40] {
+            posts.keyset("id", Some(&Cursor::new([id.into()])), 20);
+        }
+        let page = posts.build().unwrap();
+        assert_eq!(
+            page.sql,
+            r#"SELECT * FROM "posts" WHERE "published" = $1 AND "id" > $2 ORDER BY "id" LIMIT 20"#
+        );
+        assert_eq!(page.params, vec![true.into(), 40.into()]);
+
+        let first = posts.keyset("id", None, 20).build().unwrap();
+        assert_eq!(
+            first.sql,
+            r#"SELECT * FROM "posts" WHERE "published" = $1 ORDER BY "id" LIMIT 20"#
+        );
+    }
+
+    #[test]
+    fn descending_and_mixed_directions() {
+        let cursor = Cursor::new(["2024-01-01".into(), 7.into()]);
+        let query = QueryBuilder::new("posts")
+            .keyset("created_at DESC, id DESC", Some(&cursor), 10)
+            .build_with(&MySQL)
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            "SELECT * FROM `posts` WHERE (`created_at`, `id`) < (?, ?) ORDER BY `created_at` DESC, `id` DESC LIMIT 10"
+        );
+
+        let query = QueryBuilder::new("posts")
+            .keyset(
+                "score DESC, id",
+                Some(&Cursor::new([9.into(), 7.into()])),
+                10,
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT * FROM "posts" WHERE "score" < $1 OR ("score" = $2 AND "id" > $3) ORDER BY "score" DESC, "id" LIMIT 10"#
+        );
+        assert_eq!(query.params, vec![9.into(), 9.into(), 7.into()]);
+
+        let err = QueryBuilder::new("posts")
+            .keyset("created_at, id", Some(&Cursor::new([1.into()])), 10)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, BuildError::RowLengthMismatch { left: 2, right: 1 });
+    }
+
+    #[test]
+    fn cursor_round_trips_every_value_type() {
+        let cursor = Cursor::new([
+            Value::Null,
+            true.into(),
+            (-12).into(),
+            1.5.into(),
+            "a:b;c é".into(),
+            "".into(),
+        ]);
+        let token = cursor.encode();
+        assert!(token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(Cursor::decode(&token).unwrap(), cursor);
+        assert_eq!(
+            Cursor::decode("not a cursor!"),
+            Err(BuildError::InvalidCursor)
+        );
+        assert_eq!(Cursor::decode("eDEy"), Err(BuildError::InvalidCursor));
+    }
+
+    #[test]
+    fn rejects_non_ascii_tags() {
+        // "é1" is valid base64url and UTF-8 but not a cursor
+        let token = super::base64_encode("é1".as_bytes());
+        assert_eq!(Cursor::decode(&token), Err(BuildError::InvalidCursor));
+    }
+
+    #[test]
+    fn keyset_needs_an_order_and_cursor_values() {
+        let empty = Cursor::new([]);
+        assert_eq!(
+            QueryBuilder::new("posts")
+                .keyset("", Some(&empty), 10)
+                .build(),
+            Err(BuildError::MissingKeysetOrder)
+        );
+        assert_eq!(
+            QueryBuilder::new("posts").keyset("", None, 10).build(),
+            Err(BuildError::MissingKeysetOrder)
+        );
+        assert_eq!(
+            QueryBuilder::new("posts")
+                .keyset("id", Some(&empty), 10)
+                .build(),
+            Err(BuildError::RowLengthMismatch { left: 1, right: 0 })
+        );
+        // a later keyset with an ordering replaces the failed one
+        let query = QueryBuilder::new("posts")
+            .keyset("", None, 10)
+            .keyset("id", None, 10)
+            .build()
+            .unwrap();
+        assert_eq!(query.sql, r#"SELECT * FROM "posts" ORDER BY "id" LIMIT 10"#);
+    }
+}
@@ -0,0 +1,351 @@
+// Lint pass over built statements, catches the queries that work in development and hurt
+// in production: unbounded selects, `SELECT *`, deep OFFSET paging, LIMIT without a
+// stable order and UPDATE/DELETE of a whole table.
+// Only the outer statement is checked, subqueries are usually unbounded on purpose
+// (`EXISTS (SELECT * ...)`, `IN (SELECT id ...)`).
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{
+    parse, Condition, Delete, DeleteBuilder, ParseError, QueryBuilder, Select, SelectItem, Update,
+    UpdateBuilder,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    // a SELECT that can return any number of rows
+    MissingLimit,
+    SelectStar,
+    // OFFSET above `Linter::max_offset`, the skipped rows are still read
+    LargeOffset,
+    // LIMIT without ORDER BY returns an arbitrary subset
+    LimitWithoutOrderBy,
+    // UPDATE or DELETE without WHERE changes every row
+    UnfilteredMutation,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 5] = [
+        Rule::MissingLimit,
+        Rule::SelectStar,
+        Rule::LargeOffset,
+        Rule::LimitWithoutOrderBy,
+        Rule::UnfilteredMutation,
+    ];
+
+    // Stable identifier, e.g. to disable a rule from a config file.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Rule::MissingLimit => "missing-limit",
+            Rule::SelectStar => "select-star",
+            Rule::LargeOffset => "large-offset",
+            Rule::LimitWithoutOrderBy => "limit-without-order-by",
+            Rule::UnfilteredMutation => "unfiltered-mutation",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.id() == id)
+    }
+
+    fn default_severity(&self) -> Severity {
+        match self {
+            Rule::UnfilteredMutation => Severity::Error,
+            Rule::SelectStar => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {}",
+            self.severity.as_str(),
+            self.rule.id(),
+            self.message
+        )
+    }
+}
+
+// Statements the linter understands.
+pub trait Lint {
+    fn lint(&self, linter: &Linter, warnings: &mut Vec<LintWarning>);
+}
+
+// All rules enabled with their default severity and an OFFSET threshold of 1000 rows.
+#[derive(Debug, Clone)]
+pub struct Linter {
+    // a rule missing from the map is disabled
+    severities: HashMap<Rule, Severity>,
+    max_offset: i64,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self {
+            severities: Rule::ALL
+                .into_iter()
+                .map(|rule| (rule, rule.default_severity()))
+                .collect(),
+            max_offset: 1000,
+        }
+    }
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn disable(&mut self, rule: Rule) -> &mut Self {
+        self.severities.remove(&rule);
+        self
+    }
+    // Also enables a disabled rule.
+    pub fn severity(&mut self, rule: Rule, severity: Severity) -> &mut Self {
+        self.severities.insert(rule, severity);
+        self
+    }
+    pub fn max_offset(&mut self, max_offset: i64) -> &mut Self {
+        self.max_offset = max_offset;
+        self
+    }
+
+    // Warnings ordered from the most to the least severe.
+    pub fn check<S: Lint + ?Sized>(&self, statement: &S) -> Vec<LintWarning> {
+        let mut warnings = vec![];
+        statement.lint(self, &mut warnings);
+        warnings.sort_by_key(|warning| Reverse(warning.severity));
+        warnings
+    }
+    // Hand-written SELECT text, see `parse` for the supported subset.
+    pub fn check_sql(&self, sql: &str) -> Result<Vec<LintWarning>, ParseError> {
+        Ok(self.check(&parse(sql)?))
+    }
+
+    fn report(&self, warnings: &mut Vec<LintWarning>, rule: Rule, message: String) {
+        if let Some(severity) = self.severities.get(&rule) {
+            warnings.push(LintWarning {
+                rule,
+                severity: *severity,
+                message,
+            });
+        }
+    }
+
+    fn check_mutation(
+        &self,
+        warnings: &mut Vec<LintWarning>,
+        statement: &str,
+        table: &str,
+        filter: &Option<Condition>,
+    ) {
+        // `WHERE TRUE` is what an empty `and` renders to, it filters nothing either
+        let unfiltered = match filter {
+            None => true,
+            Some(Condition::And(conditions)) => conditions.is_empty(),
+            Some(_) => false,
+        };
+        if unfiltered {
+            self.report(
+                warnings,
+                Rule::UnfilteredMutation,
+                format!(
+                    "{} without WHERE affects every row of `{}`",
+                    statement, table
+                ),
+            );
+        }
+    }
+}
+
+impl Lint for Select {
+    fn lint(&self, linter: &Linter, warnings: &mut Vec<LintWarning>) {
+        let star = self.items.is_empty()
+            || self.items.iter().any(|item| match item {
+                SelectItem::Column(column) => column == "*" || column.ends_with(".*"),
+                SelectItem::Aggregate(_) | SelectItem::Window(_) => false,
+            });
+        if star {
+            linter.report(
+                warnings,
+                Rule::SelectStar,
+                format!(
+                    "SELECT * from `{}`, list the columns the caller needs",
+                    self.from.name
+                ),
+            );
+        }
+        // aggregates without GROUP BY always return a single row
+        let single_row = self.is_grouped() && self.group_by.is_empty();
+        if self.limit.is_none() && !single_row {
+            linter.report(
+                warnings,
+                Rule::MissingLimit,
+                format!("SELECT from `{}` has no LIMIT", self.from.name),
+            );
+        }
+        if let Some(offset) = self.offset.filter(|offset| *offset > linter.max_offset) {
+            linter.report(
+                warnings,
+                Rule::LargeOffset,
+                format!(
+                    "OFFSET {} is above {}, use keyset pagination",
+                    offset, linter.max_offset
+                ),
+            );
+        }
+        if self.limit.is_some() && self.order_by.is_empty() {
+            linter.report(
+                warnings,
+                Rule::LimitWithoutOrderBy,
+                "LIMIT without ORDER BY returns an arbitrary subset of rows".to_string(),
+            );
+        }
+    }
+}
+
+impl Lint for Update {
+    fn lint(&self, linter: &Linter, warnings: &mut Vec<LintWarning>) {
+        linter.check_mutation(warnings, "UPDATE", &self.table, &self.filter);
+    }
+}
+
+impl Lint for Delete {
+    fn lint(&self, linter: &Linter, warnings: &mut Vec<LintWarning>) {
+        linter.check_mutation(warnings, "DELETE", &self.table, &self.filter);
+    }
+}
+
+impl Lint for QueryBuilder<'_> {
+    fn lint(&self, linter: &Linter, warnings: &mut Vec<LintWarning>) {
+        self.to_ast().lint(linter, warnings)
+    }
+}
+
+impl Lint for UpdateBuilder<'_> {
+    fn lint(&self, linter: &Linter, warnings: &mut Vec<LintWarning>) {
+        self.to_ast().lint(linter, warnings)
+    }
+}
+
+impl Lint for DeleteBuilder<'_> {
+    fn lint(&self, linter: &Linter, warnings: &mut Vec<LintWarning>) {
+        self.to_ast().lint(linter, warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    fn rules(warnings: &[LintWarning]) -> Vec<&'static str> {
+        warnings.iter().map(|warning| warning.rule.id()).collect()
+    }
+
+    #[test]
+    fn flags_unbounded_and_unstable_selects() {
+        let linter = Linter::new();
+        let warnings = linter.check(&QueryBuilder::new("users"));
+        assert_eq!(rules(&warnings), vec!["missing-limit", "select-star"]);
+        assert_eq!(
+            warnings[1].to_string(),
+            "info[select-star]: SELECT * from `users`, list the columns the caller needs"
+        );
+
+        let warnings = linter.check(
+            QueryBuilder::new("users")
+                .select("id, u.*")
+                .limit(20)
+                .offset(5000),
+        );
+        assert_eq!(
+            rules(&warnings),
+            vec!["large-offset", "limit-without-order-by", "select-star"]
+        );
+        assert_eq!(
+            warnings[0].message,
+            "OFFSET 5000 is above 1000, use keyset pagination"
+        );
+
+        let clean = linter.check(QueryBuilder::new("users").select("id").keyset(
+            "id",
+            Some(&Cursor::new(vec![10.into()])),
+            20,
+        ));
+        assert_eq!(clean, vec![]);
+        let count = linter.check(QueryBuilder::new("users").aggregate(count("*")));
+        assert_eq!(count, vec![]);
+    }
+
+    #[test]
+    fn flags_unfiltered_mutations() {
+        let linter = Linter::new();
+        let mut update = UpdateBuilder::new("users");
+        update.set("active", false);
+        let warnings = linter.check(&update);
+        assert_eq!(
+            warnings,
+            vec![LintWarning {
+                rule: Rule::UnfilteredMutation,
+                severity: Severity::Error,
+                message: "UPDATE without WHERE affects every row of `users`".to_string(),
+            }]
+        );
+        update.filter(col("id").eq(1));
+        assert_eq!(linter.check(&update), vec![]);
+
+        let mut delete = DeleteBuilder::new("sessions");
+        delete.filter(Condition::And(vec![]));
+        assert_eq!(rules(&linter.check(&delete)), vec!["unfiltered-mutation"]);
+    }
+
+    #[test]
+    fn rules_are_configurable() {
+        let mut linter = Linter::new();
+        linter
+            .disable(Rule::SelectStar)
+            .severity(Rule::MissingLimit, Severity::Error)
+            .max_offset(10_000);
+        let warnings = linter
+            .check_sql("SELECT * FROM events ORDER BY id OFFSET 5000")
+            .unwrap();
+        assert_eq!(
+            warnings,
+            vec![LintWarning {
+                rule: Rule::MissingLimit,
+                severity: Severity::Error,
+                message: "SELECT from `events` has no LIMIT".to_string(),
+            }]
+        );
+        assert_eq!(Rule::from_id("large-offset"), Some(Rule::LargeOffset));
+        assert_eq!(Rule::from_id("nope"), None);
+        assert!(linter.check_sql("SELECT FROM").is_err());
+    }
+}
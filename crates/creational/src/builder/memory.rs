@@ -0,0 +1,1250 @@
+// In-memory table engine, lets tests check what a query returns without a database.
+// Tables are plain rows of `Value`s. A query is evaluated straight from its `Select` AST
+// in SQL order: WITH, FROM and JOIN, WHERE, GROUP BY and HAVING, window functions, SELECT,
+// set operations, ORDER BY, DISTINCT ON, LIMIT/OFFSET. Comparisons follow SQL three-valued
+// logic, anything compared with NULL is unknown and does not match.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{
+    AggregateFn, BuildError, Condition, Cte, Direction, ExecError, Executor, Expr, Join, JoinKind,
+    Operator, OrderBy, Over, Rows, Select, SelectItem, SetOperator, TableRef, Value, WindowFn,
+    WindowSpec,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryDatabase {
+    tables: HashMap<String, Rows>,
+}
+
+impl MemoryDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces any table with the same name.
+    pub fn create_table(&mut self, name: &str, columns: &[&str]) -> &mut Self {
+        self.tables.insert(name.to_string(), Rows::new(columns));
+        self
+    }
+
+    pub fn insert(&mut self, table: &str, row: Vec<Value>) -> Result<&mut Self, ExecError> {
+        let rows = self
+            .tables
+            .get_mut(table)
+            .ok_or_else(|| ExecError::UnknownTable(table.to_string()))?;
+        if row.len() != rows.columns.len() {
+            return Err(ExecError::ColumnCountMismatch {
+                expected: rows.columns.len(),
+                found: row.len(),
+            });
+        }
+        rows.rows.push(row);
+        Ok(self)
+    }
+
+    pub fn table(&self, name: &str) -> Option<&Rows> {
+        self.tables.get(name)
+    }
+}
+
+impl Executor for MemoryDatabase {
+    fn fetch(&mut self, query: &Select) -> Result<Rows, ExecError> {
+        Env {
+            db: self,
+            ctes: HashMap::new(),
+        }
+        .run(query, None)
+    }
+}
+
+// Rows produced by FROM and JOIN, every column remembers the table (or alias) it came from.
+struct Source {
+    columns: Vec<(String, String)>,
+    rows: Vec<Vec<Value>>,
+}
+
+// What a column reference can see while one row is evaluated. Subqueries get the row of
+// the enclosing query as `outer`, which makes correlated subqueries work.
+#[derive(Clone, Copy)]
+struct Scope<'s> {
+    columns: &'s [(String, String)],
+    row: &'s [Value],
+    // all rows of the current group, aggregates are computed over them
+    group: Option<&'s [Vec<Value>]>,
+    outer: Option<&'s Scope<'s>>,
+}
+
+impl Scope<'_> {
+    fn lookup(&self, name: &str) -> Result<Value, ExecError> {
+        let (qualifier, column) = match name.rsplit_once('.') {
+            Some((qualifier, column)) => (Some(qualifier), column),
+            None => (None, name),
+        };
+        let mut found = self
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, (table, name))| {
+                name == column && qualifier.is_none_or(|qualifier| qualifier == table)
+            });
+        match (found.next(), found.next()) {
+            (Some((index, _)), None) => Ok(self.row[index].clone()),
+            (Some(_), Some(_)) => Err(ExecError::AmbiguousColumn(name.to_string())),
+            (None, _) => match self.outer {
+                Some(outer) => outer.lookup(name),
+                None => Err(ExecError::UnknownColumn(name.to_string())),
+            },
+        }
+    }
+}
+
+// Output row paired with its ORDER BY keys followed by its DISTINCT ON keys, the keys may
+// use columns that are not selected.
+type Keyed = (Vec<Value>, Vec<Value>);
+
+// Tables visible to a query: the database plus the CTEs defined around it.
+#[derive(Clone)]
+struct Env<'db> {
+    db: &'db MemoryDatabase,
+    ctes: HashMap<String, Rc<Rows>>,
+}
+
+impl Env<'_> {
+    fn run(&self, query: &Select, outer: Option<&Scope<'_>>) -> Result<Rows, ExecError> {
+        query.validate()?;
+        let env = self.with_ctes(&query.ctes, outer)?;
+        // ORDER BY of a compound query can only refer to the output columns
+        let compound = !query.set_operations.is_empty();
+        if compound && !query.distinct_on.is_empty() {
+            return Err(ExecError::Unsupported("DISTINCT ON with set operations"));
+        }
+        let keys: Vec<&str> = if compound {
+            vec![]
+        } else {
+            let order_by = query.order_by.iter().map(|order| order.column.as_str());
+            order_by
+                .chain(query.distinct_on.iter().map(String::as_str))
+                .collect()
+        };
+        let (columns, mut rows) = env.project(query, &keys, outer)?;
+        if compound {
+            let mut values: Vec<Vec<Value>> = rows.into_iter().map(|(row, _)| row).collect();
+            for (operator, operand) in &query.set_operations {
+                let (operand_columns, operand_rows) = env.project(operand, &[], outer)?;
+                if operand_columns.len() != columns.len() {
+                    return Err(ExecError::ColumnCountMismatch {
+                        expected: columns.len(),
+                        found: operand_columns.len(),
+                    });
+                }
+                let operand_rows = operand_rows.into_iter().map(|(row, _)| row).collect();
+                values = combine(*operator, values, operand_rows);
+            }
+            let output: Vec<(String, String)> = columns
+                .iter()
+                .map(|column| (String::new(), column.clone()))
+                .collect();
+            rows = values
+                .into_iter()
+                .map(|row| {
+                    let scope = Scope {
+                        columns: &output,
+                        row: &row,
+                        group: None,
+                        outer: None,
+                    };
+                    let keys = query
+                        .order_by
+                        .iter()
+                        .map(|order| scope.lookup(unqualified(&order.column)))
+                        .collect::<Result<_, _>>()?;
+                    Ok((row, keys))
+                })
+                .collect::<Result<_, ExecError>>()?;
+        }
+        rows.sort_by(|(_, left), (_, right)| compare_keys(&query.order_by, left, right));
+        if !query.distinct_on.is_empty() {
+            // the first row of every DISTINCT ON group in ORDER BY order
+            let mut seen: Vec<Vec<Value>> = vec![];
+            rows.retain(|(_, keys)| {
+                let distinct = keys[query.order_by.len()..].to_vec();
+                let first = !seen.contains(&distinct);
+                if first {
+                    seen.push(distinct);
+                }
+                first
+            });
+        }
+        let offset = query.offset.unwrap_or(0) as usize;
+        let limit = query.limit.map_or(usize::MAX, |limit| limit as usize);
+        Ok(Rows {
+            columns,
+            rows: rows
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .map(|(row, _)| row)
+                .collect(),
+        })
+    }
+
+    fn with_ctes(&self, ctes: &[Cte], outer: Option<&Scope<'_>>) -> Result<Self, ExecError> {
+        let mut env = self.clone();
+        for cte in ctes {
+            let rows = if cte.recursive && !cte.query.set_operations.is_empty() {
+                env.run_recursive(cte, outer)?
+            } else {
+                env.run(&cte.query, outer)?
+            };
+            env.ctes.insert(cte.name.clone(), Rc::new(rows));
+        }
+        Ok(env)
+    }
+
+    // The first operand seeds the result, the other operands run again and again against
+    // the rows found by the previous round until a round finds nothing new.
+    fn run_recursive(&self, cte: &Cte, outer: Option<&Scope<'_>>) -> Result<Rows, ExecError> {
+        let seed = Select {
+            set_operations: vec![],
+            ..cte.query.clone()
+        };
+        let Rows { columns, rows } = self.run(&seed, outer)?;
+        let distinct = cte
+            .query
+            .set_operations
+            .iter()
+            .any(|(operator, _)| *operator == SetOperator::Union);
+        let mut result = if distinct { dedup(rows) } else { rows };
+        let mut working = result.clone();
+        while !working.is_empty() {
+            let mut env = self.clone();
+            let previous = Rows {
+                columns: columns.clone(),
+                rows: working,
+            };
+            env.ctes.insert(cte.name.clone(), Rc::new(previous));
+            let mut found = vec![];
+            for (_, step) in &cte.query.set_operations {
+                let rows = env.run(step, outer)?;
+                if rows.columns.len() != columns.len() {
+                    return Err(ExecError::ColumnCountMismatch {
+                        expected: columns.len(),
+                        found: rows.columns.len(),
+                    });
+                }
+                found.extend(rows.rows);
+            }
+            if distinct {
+                found = dedup(found);
+                found.retain(|row| !result.contains(row));
+            }
+            result.extend(found.iter().cloned());
+            working = found;
+        }
+        Ok(Rows {
+            columns,
+            rows: result,
+        })
+    }
+
+    fn table(&self, table: &TableRef) -> Result<Source, ExecError> {
+        let rows = match self.ctes.get(&table.name) {
+            Some(rows) => rows.as_ref(),
+            None => self
+                .db
+                .tables
+                .get(&table.name)
+                .ok_or_else(|| ExecError::UnknownTable(table.name.clone()))?,
+        };
+        let qualifier = table.alias.as_ref().unwrap_or(&table.name);
+        Ok(Source {
+            columns: rows
+                .columns
+                .iter()
+                .map(|column| (qualifier.clone(), column.clone()))
+                .collect(),
+            rows: rows.rows.clone(),
+        })
+    }
+
+    // FROM, JOIN and WHERE.
+    fn source(&self, query: &Select, outer: Option<&Scope<'_>>) -> Result<Source, ExecError> {
+        let mut source = self.table(&query.from)?;
+        for join in &query.joins {
+            let right = self.table(&join.table)?;
+            source = self.join(source, right, join, outer)?;
+        }
+        if let Some(filter) = &query.filter {
+            let mut rows = vec![];
+            for row in source.rows {
+                let scope = Scope {
+                    columns: &source.columns,
+                    row: &row,
+                    group: None,
+                    outer,
+                };
+                if self.condition(filter, &scope)? == Some(true) {
+                    rows.push(row);
+                }
+            }
+            source.rows = rows;
+        }
+        Ok(source)
+    }
+
+    fn join(
+        &self,
+        left: Source,
+        right: Source,
+        join: &Join,
+        outer: Option<&Scope<'_>>,
+    ) -> Result<Source, ExecError> {
+        let columns = [left.columns.clone(), right.columns.clone()].concat();
+        let mut rows = vec![];
+        let mut right_matched = vec![false; right.rows.len()];
+        for left_row in &left.rows {
+            let mut matched = false;
+            for (i, right_row) in right.rows.iter().enumerate() {
+                let row = [left_row.as_slice(), right_row.as_slice()].concat();
+                let keep = match &join.on {
+                    Some(on) => {
+                        let scope = Scope {
+                            columns: &columns,
+                            row: &row,
+                            group: None,
+                            outer,
+                        };
+                        self.condition(on, &scope)? == Some(true)
+                    }
+                    None => true,
+                };
+                if keep {
+                    matched = true;
+                    right_matched[i] = true;
+                    rows.push(row);
+                }
+            }
+            if !matched && matches!(join.kind, JoinKind::Left | JoinKind::Full) {
+                rows.push([left_row.clone(), nulls(right.columns.len())].concat());
+            }
+        }
+        if matches!(join.kind, JoinKind::Right | JoinKind::Full) {
+            for (right_row, _) in right
+                .rows
+                .into_iter()
+                .zip(right_matched)
+                .filter(|(_, matched)| !matched)
+            {
+                rows.push([nulls(left.columns.len()), right_row].concat());
+            }
+        }
+        Ok(Source { columns, rows })
+    }
+
+    // Evaluates everything up to the select list, returns the output columns and rows.
+    fn project(
+        &self,
+        query: &Select,
+        keys: &[&str],
+        outer: Option<&Scope<'_>>,
+    ) -> Result<(Vec<String>, Vec<Keyed>), ExecError> {
+        let source = self.source(query, outer)?;
+        let items = output_items(query, &source)?;
+        let columns: Vec<String> = items.iter().map(|(name, _)| name.clone()).collect();
+        let windows = self.windows(query, &source, outer)?;
+        let mut rows = vec![];
+        if query.is_grouped() {
+            if !windows.is_empty() {
+                return Err(ExecError::Unsupported("window functions over grouped rows"));
+            }
+            for group in self.groups(query, &source, outer)? {
+                let representative = match group.first() {
+                    Some(row) => row.clone(),
+                    None => nulls(source.columns.len()),
+                };
+                let scope = Scope {
+                    columns: &source.columns,
+                    row: &representative,
+                    group: Some(&group),
+                    outer,
+                };
+                if let Some(having) = &query.having {
+                    if self.condition(having, &scope)? != Some(true) {
+                        continue;
+                    }
+                }
+                rows.push(self.output_row(&items, &columns, keys, &scope, &[])?);
+            }
+        } else {
+            for (index, row) in source.rows.iter().enumerate() {
+                let scope = Scope {
+                    columns: &source.columns,
+                    row,
+                    group: None,
+                    outer,
+                };
+                let window_row: Vec<Value> =
+                    windows.iter().map(|values| values[index].clone()).collect();
+                rows.push(self.output_row(&items, &columns, keys, &scope, &window_row)?);
+            }
+        }
+        Ok((columns, rows))
+    }
+
+    // One value per source row for every window function of the select list.
+    fn windows(
+        &self,
+        query: &Select,
+        source: &Source,
+        outer: Option<&Scope<'_>>,
+    ) -> Result<Vec<Vec<Value>>, ExecError> {
+        let mut windows = vec![];
+        for item in &query.items {
+            let SelectItem::Window(function) = item else {
+                continue;
+            };
+            let spec = match &function.over {
+                Over::Spec(spec) => spec,
+                Over::Named(name) => query
+                    .windows
+                    .iter()
+                    .find(|(window, _)| window == name)
+                    .map(|(_, spec)| spec)
+                    .ok_or_else(|| BuildError::UnknownWindow(name.clone()))?,
+            };
+            windows.push(self.window(&function.func, spec, source, outer)?);
+        }
+        Ok(windows)
+    }
+
+    fn window(
+        &self,
+        func: &WindowFn,
+        spec: &WindowSpec,
+        source: &Source,
+        outer: Option<&Scope<'_>>,
+    ) -> Result<Vec<Value>, ExecError> {
+        let scope = |row| Scope {
+            columns: &source.columns,
+            row,
+            group: None,
+            outer,
+        };
+        let mut partitions: Vec<(Vec<Value>, Vec<usize>)> = vec![];
+        let mut order_keys = vec![];
+        for (index, row) in source.rows.iter().enumerate() {
+            let scope = scope(row);
+            let key = spec
+                .partition_by
+                .iter()
+                .map(|column| scope.lookup(column))
+                .collect::<Result<Vec<_>, _>>()?;
+            let order_key = spec
+                .order_by
+                .iter()
+                .map(|order| scope.lookup(&order.column))
+                .collect::<Result<Vec<_>, _>>()?;
+            order_keys.push(order_key);
+            match partitions.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, members)) => members.push(index),
+                None => partitions.push((key, vec![index])),
+            }
+        }
+        let mut values = vec![Value::Null; source.rows.len()];
+        for (_, mut members) in partitions {
+            members.sort_by(|a, b| compare_keys(&spec.order_by, &order_keys[*a], &order_keys[*b]));
+            // rows with equal ORDER BY values share their rank and their running aggregate
+            let peers = |i: usize, j: usize| {
+                compare_keys(
+                    &spec.order_by,
+                    &order_keys[members[i]],
+                    &order_keys[members[j]],
+                ) == Ordering::Equal
+            };
+            // `forward` for LEAD, a target past either end of the partition (or of i64) is NULL
+            let shifted = |position: usize, offset: i64, forward: bool, column: &str| {
+                let target = i64::try_from(position)
+                    .ok()
+                    .and_then(|position| {
+                        if forward {
+                            position.checked_add(offset)
+                        } else {
+                            position.checked_sub(offset)
+                        }
+                    })
+                    .and_then(|target| usize::try_from(target).ok());
+                match target.and_then(|target| members.get(target)) {
+                    Some(other) => scope(&source.rows[*other]).lookup(column),
+                    None => Ok(Value::Null),
+                }
+            };
+            let (mut rank, mut dense_rank) = (0, 0);
+            for (position, index) in members.iter().enumerate() {
+                if position == 0 || !peers(position - 1, position) {
+                    rank = position + 1;
+                    dense_rank += 1;
+                }
+                values[*index] = match func {
+                    WindowFn::RowNumber => Value::Int(position as i64 + 1),
+                    WindowFn::Rank => Value::Int(rank as i64),
+                    WindowFn::DenseRank => Value::Int(dense_rank),
+                    WindowFn::Lag(column, offset) => shifted(position, *offset, false, column)?,
+                    WindowFn::Lead(column, offset) => shifted(position, *offset, true, column)?,
+                    WindowFn::Aggregate(func, column) => {
+                        // without ORDER BY the frame is the whole partition, with it the
+                        // rows up to the last peer of the current one
+                        let end = (position..members.len())
+                            .take_while(|other| spec.order_by.is_empty() || peers(position, *other))
+                            .last()
+                            .map_or(members.len(), |last| last + 1);
+                        let frame: Vec<Vec<Value>> = members[..end]
+                            .iter()
+                            .map(|member| source.rows[*member].clone())
+                            .collect();
+                        let scope = Scope {
+                            group: Some(&frame),
+                            ..scope(&source.rows[*index])
+                        };
+                        self.aggregate(*func, column, &scope)?
+                    }
+                };
+            }
+        }
+        Ok(values)
+    }
+
+    // Rows with equal GROUP BY values, in order of first appearance. Without GROUP BY all
+    // rows form a single group, even when there are none: `SELECT COUNT(*)` returns 0.
+    fn groups(
+        &self,
+        query: &Select,
+        source: &Source,
+        outer: Option<&Scope<'_>>,
+    ) -> Result<Vec<Vec<Vec<Value>>>, ExecError> {
+        if query.group_by.is_empty() {
+            return Ok(vec![source.rows.clone()]);
+        }
+        let mut groups: Vec<(Vec<Value>, Vec<Vec<Value>>)> = vec![];
+        for row in &source.rows {
+            let scope = Scope {
+                columns: &source.columns,
+                row,
+                group: None,
+                outer,
+            };
+            let key = query
+                .group_by
+                .iter()
+                .map(|column| scope.lookup(column))
+                .collect::<Result<Vec<_>, _>>()?;
+            match groups.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, rows)) => rows.push(row.clone()),
+                None => groups.push((key, vec![row.clone()])),
+            }
+        }
+        Ok(groups.into_iter().map(|(_, rows)| rows).collect())
+    }
+
+    fn output_row(
+        &self,
+        items: &[(String, Output<'_>)],
+        columns: &[String],
+        keys: &[&str],
+        scope: &Scope<'_>,
+        windows: &[Value],
+    ) -> Result<Keyed, ExecError> {
+        let row = items
+            .iter()
+            .map(|(_, output)| match output {
+                Output::Index(index) => Ok(scope.row[*index].clone()),
+                Output::Column(name) => scope.lookup(name),
+                Output::Aggregate(func, column) => self.aggregate(*func, column, scope),
+                Output::Window(index) => Ok(windows[*index].clone()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // an output name such as an aggregate alias wins over a source column
+        let keys = keys
+            .iter()
+            .map(|key| match columns.iter().position(|c| c == key) {
+                Some(index) => Ok(row[index].clone()),
+                None => scope.lookup(key),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((row, keys))
+    }
+
+    fn expr(&self, expr: &Expr, scope: &Scope<'_>) -> Result<Value, ExecError> {
+        match expr {
+            Expr::Column(name) => scope.lookup(name),
+            Expr::Param(value) => Ok(value.clone()),
+            Expr::Aggregate(func, column) => self.aggregate(*func, column, scope),
+            Expr::Subquery(query) => {
+                let mut values = self.column_of(query, scope)?;
+                match values.len() {
+                    0 => Ok(Value::Null),
+                    1 => Ok(values.remove(0)),
+                    rows => Err(ExecError::SubqueryRows(rows)),
+                }
+            }
+        }
+    }
+
+    // Runs a single column subquery with the current row as outer scope.
+    fn column_of(&self, query: &Select, scope: &Scope<'_>) -> Result<Vec<Value>, ExecError> {
+        let rows = self.run(query, Some(scope))?;
+        if rows.columns.len() != 1 {
+            return Err(ExecError::SubqueryColumns(rows.columns.len()));
+        }
+        Ok(rows.rows.into_iter().flatten().collect())
+    }
+
+    fn aggregate(
+        &self,
+        func: AggregateFn,
+        column: &str,
+        scope: &Scope<'_>,
+    ) -> Result<Value, ExecError> {
+        let group = scope.group.ok_or(ExecError::MisplacedAggregate)?;
+        if column == "*" {
+            return Ok(Value::Int(group.len() as i64));
+        }
+        let mut values = vec![];
+        for row in group {
+            let scope = Scope {
+                row,
+                group: None,
+                ..*scope
+            };
+            match scope.lookup(column)? {
+                Value::Null => {}
+                value => values.push(value),
+            }
+        }
+        let mut values = values.into_iter();
+        let Some(first) = values.next() else {
+            return Ok(match func {
+                AggregateFn::Count => Value::Int(0),
+                _ => Value::Null,
+            });
+        };
+        match func {
+            AggregateFn::Count => Ok(Value::Int(values.count() as i64 + 1)),
+            AggregateFn::Sum => values.try_fold(first, |total, value| add(&total, &value)),
+            AggregateFn::Avg => {
+                let mut count = 1;
+                let total = values.try_fold(first, |total, value| {
+                    count += 1;
+                    add(&total, &value)
+                })?;
+                match number(&total) {
+                    Some(total) => Ok(Value::Float(total / count as f64)),
+                    None => Err(ExecError::TypeMismatch(total, Value::Int(count))),
+                }
+            }
+            AggregateFn::Min | AggregateFn::Max => values.try_fold(first, |best, value| {
+                let wanted = match func {
+                    AggregateFn::Min => Ordering::Less,
+                    _ => Ordering::Greater,
+                };
+                Ok(if compare(&value, &best)? == Some(wanted) {
+                    value
+                } else {
+                    best
+                })
+            }),
+        }
+    }
+
+    // Three-valued: Some(true), Some(false) or None when the result is unknown.
+    fn condition(
+        &self,
+        condition: &Condition,
+        scope: &Scope<'_>,
+    ) -> Result<Option<bool>, ExecError> {
+        match condition {
+            Condition::Compare(left, op, right) => {
+                let (left, right) = (self.expr(left, scope)?, self.expr(right, scope)?);
+                Ok(compare(&left, &right)?.map(|ordering| satisfies(*op, ordering)))
+            }
+            Condition::In(expr, list) => {
+                let value = self.expr(expr, scope)?;
+                let list = list
+                    .iter()
+                    .map(|item| self.expr(item, scope))
+                    .collect::<Result<Vec<_>, _>>()?;
+                contains(&value, &list)
+            }
+            Condition::InQuery(expr, query) => {
+                let value = self.expr(expr, scope)?;
+                contains(&value, &self.column_of(query, scope)?)
+            }
+            Condition::Exists(query) => Ok(Some(!self.run(query, Some(scope))?.rows.is_empty())),
+            Condition::Like(expr, pattern) => {
+                match (self.expr(expr, scope)?, self.expr(pattern, scope)?) {
+                    (Value::Null, _) | (_, Value::Null) => Ok(None),
+                    (Value::Text(text), Value::Text(pattern)) => Ok(Some(like(&text, &pattern))),
+                    (text, pattern) => Err(ExecError::TypeMismatch(text, pattern)),
+                }
+            }
+            Condition::IsNull(expr) => Ok(Some(self.expr(expr, scope)? == Value::Null)),
+            Condition::Between(expr, low, high) => {
+                let value = self.expr(expr, scope)?;
+                let above = compare(&value, &self.expr(low, scope)?)?;
+                let below = compare(&value, &self.expr(high, scope)?)?;
+                Ok(all([
+                    above.map(|ordering| ordering != Ordering::Less),
+                    below.map(|ordering| ordering != Ordering::Greater),
+                ]))
+            }
+            Condition::RowCompare(left, op, right) => {
+                if left.len() != right.len() {
+                    return Err(ExecError::ColumnCountMismatch {
+                        expected: left.len(),
+                        found: right.len(),
+                    });
+                }
+                // lexicographic, the first pair that differs decides
+                for (left, right) in left.iter().zip(right) {
+                    let (left, right) = (self.expr(left, scope)?, self.expr(right, scope)?);
+                    match compare(&left, &right)? {
+                        None => return Ok(None),
+                        Some(Ordering::Equal) => {}
+                        Some(ordering) => return Ok(Some(satisfies(*op, ordering))),
+                    }
+                }
+                Ok(Some(satisfies(*op, Ordering::Equal)))
+            }
+            Condition::And(conditions) => Ok(all(conditions
+                .iter()
+                .map(|condition| self.condition(condition, scope))
+                .collect::<Result<Vec<_>, _>>()?)),
+            Condition::Or(conditions) => Ok(any(conditions
+                .iter()
+                .map(|condition| self.condition(condition, scope))
+                .collect::<Result<Vec<_>, _>>()?)),
+            Condition::Not(condition) => Ok(self.condition(condition, scope)?.map(|value| !value)),
+        }
+    }
+}
+
+// How one output column is computed.
+enum Output<'q> {
+    // a column expanded from `*` or `t.*`
+    Index(usize),
+    Column(&'q str),
+    Aggregate(AggregateFn, &'q str),
+    // the n-th window function of the select list
+    Window(usize),
+}
+
+// Output columns are named like a database names them: the column without its qualifier,
+// the alias of an aggregate or window function, or the lower-case function name.
+fn output_items<'q>(
+    query: &'q Select,
+    source: &Source,
+) -> Result<Vec<(String, Output<'q>)>, ExecError> {
+    let all_columns = |qualifier: Option<&str>| -> Vec<(String, Output<'q>)> {
+        source
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, (table, _))| qualifier.is_none_or(|qualifier| qualifier == table))
+            .map(|(index, (_, name))| (name.clone(), Output::Index(index)))
+            .collect()
+    };
+    if query.items.is_empty() {
+        return Ok(all_columns(None));
+    }
+    let mut output = vec![];
+    let mut windows = 0;
+    for item in &query.items {
+        match item {
+            SelectItem::Column(column) if column == "*" || column.ends_with(".*") => {
+                let before = output.len();
+                output.extend(all_columns(column.strip_suffix(".*")));
+                if output.len() == before {
+                    return Err(ExecError::UnknownColumn(column.clone()));
+                }
+            }
+            SelectItem::Column(column) => output.push((
+                unqualified(column).to_string(),
+                Output::Column(column.as_str()),
+            )),
+            SelectItem::Aggregate(aggregate) => output.push((
+                aggregate
+                    .alias
+                    .clone()
+                    .unwrap_or_else(|| aggregate.func.as_str().to_lowercase()),
+                Output::Aggregate(aggregate.func, aggregate.column.as_str()),
+            )),
+            SelectItem::Window(function) => {
+                let name = function.alias.clone();
+                output.push((
+                    name.unwrap_or_else(|| function.func.name().to_lowercase()),
+                    Output::Window(windows),
+                ));
+                windows += 1;
+            }
+        }
+    }
+    Ok(output)
+}
+
+fn unqualified(column: &str) -> &str {
+    column.rsplit('.').next().unwrap_or(column)
+}
+
+fn nulls(count: usize) -> Vec<Value> {
+    vec![Value::Null; count]
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(value) => Some(*value as f64),
+        Value::Float(value) => Some(*value),
+        _ => None,
+    }
+}
+
+// None when either side is NULL. Integers and floats compare as numbers, other types only
+// with themselves.
+fn compare(left: &Value, right: &Value) -> Result<Option<Ordering>, ExecError> {
+    match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => Ok(None),
+        (Value::Bool(left), Value::Bool(right)) => Ok(Some(left.cmp(right))),
+        (Value::Int(left), Value::Int(right)) => Ok(Some(left.cmp(right))),
+        (Value::Text(left), Value::Text(right)) => Ok(Some(left.cmp(right))),
+        _ => match (number(left), number(right)) {
+            (Some(l), Some(r)) => Ok(l.partial_cmp(&r)),
+            _ => Err(ExecError::TypeMismatch(left.clone(), right.clone())),
+        },
+    }
+}
+
+fn add(left: &Value, right: &Value) -> Result<Value, ExecError> {
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => match l.checked_add(*r) {
+            Some(sum) => Ok(Value::Int(sum)),
+            None => Err(ExecError::Overflow(left.clone(), right.clone())),
+        },
+        _ => match (number(left), number(right)) {
+            (Some(l), Some(r)) => Ok(Value::Float(l + r)),
+            _ => Err(ExecError::TypeMismatch(left.clone(), right.clone())),
+        },
+    }
+}
+
+fn satisfies(op: Operator, ordering: Ordering) -> bool {
+    match op {
+        Operator::Eq => ordering == Ordering::Equal,
+        Operator::Ne => ordering != Ordering::Equal,
+        Operator::Lt => ordering == Ordering::Less,
+        Operator::Le => ordering != Ordering::Greater,
+        Operator::Gt => ordering == Ordering::Greater,
+        Operator::Ge => ordering != Ordering::Less,
+    }
+}
+
+// `x IN (...)`: true on a match, unknown if there is none but the list holds a NULL.
+fn contains(value: &Value, list: &[Value]) -> Result<Option<bool>, ExecError> {
+    let mut unknown = false;
+    for item in list {
+        match compare(value, item)? {
+            Some(Ordering::Equal) => return Ok(Some(true)),
+            None => unknown = true,
+            Some(_) => {}
+        }
+    }
+    Ok(if unknown { None } else { Some(false) })
+}
+
+fn all(values: impl IntoIterator<Item = Option<bool>>) -> Option<bool> {
+    let mut result = Some(true);
+    for value in values {
+        match value {
+            Some(false) => return Some(false),
+            None => result = None,
+            Some(true) => {}
+        }
+    }
+    result
+}
+
+fn any(values: impl IntoIterator<Item = Option<bool>>) -> Option<bool> {
+    let mut result = Some(false);
+    for value in values {
+        match value {
+            Some(true) => return Some(true),
+            None => result = None,
+            Some(false) => {}
+        }
+    }
+    result
+}
+
+// `%` matches any run of characters, `_` exactly one.
+fn like(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    fn matches(text: &[char], pattern: &[char]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some(('%', rest)) => (0..=text.len()).any(|skip| matches(&text[skip..], rest)),
+            Some(('_', rest)) => !text.is_empty() && matches(&text[1..], rest),
+            Some((c, rest)) => text.first() == Some(c) && matches(&text[1..], rest),
+        }
+    }
+    matches(&text, &pattern)
+}
+
+// NULLs sort last in ascending order and first in descending order, like PostgreSQL.
+fn compare_keys(order_by: &[OrderBy], left: &[Value], right: &[Value]) -> Ordering {
+    for ((order, left), right) in order_by.iter().zip(left).zip(right) {
+        let ordering = match (left, right) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Greater,
+            (_, Value::Null) => Ordering::Less,
+            _ => compare(left, right)
+                .ok()
+                .flatten()
+                .unwrap_or(Ordering::Equal),
+        };
+        let ordering = match order.direction {
+            Some(Direction::Desc) => ordering.reverse(),
+            _ => ordering,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+// Keeps the first occurrence of every row.
+fn dedup(rows: Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+    let mut distinct: Vec<Vec<Value>> = vec![];
+    for row in rows {
+        if !distinct.contains(&row) {
+            distinct.push(row);
+        }
+    }
+    distinct
+}
+
+fn combine(
+    operator: SetOperator,
+    left: Vec<Vec<Value>>,
+    right: Vec<Vec<Value>>,
+) -> Vec<Vec<Value>> {
+    match operator {
+        SetOperator::UnionAll => [left, right].concat(),
+        SetOperator::Union => dedup([left, right].concat()),
+        SetOperator::Intersect => dedup(left)
+            .into_iter()
+            .filter(|row| right.contains(row))
+            .collect(),
+        SetOperator::Except => dedup(left)
+            .into_iter()
+            .filter(|row| !right.contains(row))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        avg, col, count, exists, max, subquery, sum, table, users, BuildError, PostgreSQL,
+        QueryBuilder, TypedColumn, UserQueryBuilder,
+    };
+    use super::*;
+
+    fn database() -> MemoryDatabase {
+        let mut db = MemoryDatabase::new();
+        db.create_table("users", &["id", "name", "age", "manager_id"])
+            .create_table("posts", &["id", "user_id", "title", "likes"]);
+        let users = [
+            (1, "ann", 34, None),
+            (2, "bob", 17, Some(1)),
+            (3, "cid", 25, Some(1)),
+            (4, "dee", 41, Some(3)),
+        ];
+        for (id, name, age, manager_id) in users {
+            db.insert(
+                "users",
+                vec![id.into(), name.into(), age.into(), manager_id.into()],
+            )
+            .unwrap();
+        }
+        let posts = [
+            (1, 1, "hello", 10),
+            (2, 1, "again", 5),
+            (3, 3, "rust", 30),
+            (4, 9, "orphan", 1),
+        ];
+        for (id, user_id, title, likes) in posts {
+            db.insert(
+                "posts",
+                vec![id.into(), user_id.into(), title.into(), likes.into()],
+            )
+            .unwrap();
+        }
+        db
+    }
+
+    fn texts(rows: &Rows, column: &str) -> Vec<String> {
+        rows.column(column)
+            .unwrap()
+            .into_iter()
+            .map(|value| match value {
+                Value::Text(text) => text.clone(),
+                other => format!("{:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn filters_orders_and_pages() {
+        let mut db = database();
+        let rows = QueryBuilder::new("users")
+            .select("name, age")
+            .filter(col("age").ge(18))
+            .order_by("age DESC")
+            .limit(2)
+            .offset(1)
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(rows.columns, vec!["name", "age"]);
+        assert_eq!(
+            rows.rows,
+            vec![
+                vec![Value::from("ann"), Value::from(34)],
+                vec![Value::from("cid"), Value::from(25)],
+            ]
+        );
+        let rows = QueryBuilder::new("users")
+            .filter(!col("name").like("_o%").or(col("age").between(20, 30)))
+            .order_by("name DESC")
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(texts(&rows, "name"), vec!["dee", "ann"]);
+
+        // manager_id = NULL is unknown, not true
+        let rows = QueryBuilder::new("users")
+            .filter(col("manager_id").ne(1))
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(texts(&rows, "name"), vec!["dee"]);
+        let rows = QueryBuilder::new("users")
+            .filter(col("manager_id").is_null())
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(texts(&rows, "name"), vec!["ann"]);
+    }
+
+    #[test]
+    fn joins_keep_unmatched_rows_as_nulls() {
+        let mut db = database();
+        let rows = QueryBuilder::new("users")
+            .alias("u")
+            .select("u.name, p.title")
+            .left_join(table("posts").alias("p"), col("p.user_id").eq(col("u.id")))
+            .order_by("u.id, p.id")
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(
+            texts(&rows, "name"),
+            vec!["ann", "ann", "bob", "cid", "dee"]
+        );
+        assert_eq!(
+            texts(&rows, "title"),
+            vec!["hello", "again", "Null", "rust", "Null"]
+        );
+
+        let rows = QueryBuilder::new("users")
+            .alias("u")
+            .select("p.title")
+            .right_join(table("posts").alias("p"), col("p.user_id").eq(col("u.id")))
+            .filter(col("u.id").is_null())
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(texts(&rows, "title"), vec!["orphan"]);
+
+        let error = QueryBuilder::new("users")
+            .select("id")
+            .inner_join("posts", col("posts.user_id").eq(col("users.id")))
+            .fetch(&mut db)
+            .unwrap_err();
+        assert_eq!(error, ExecError::AmbiguousColumn("id".to_string()));
+    }
+
+    #[test]
+    fn groups_and_aggregates() {
+        let mut db = database();
+        let rows = QueryBuilder::new("posts")
+            .select("user_id")
+            .aggregate(count("*").alias("total"))
+            .aggregate(sum("likes"))
+            .group_by("user_id")
+            .having(count("*").ge(1).and(sum("likes").gt(1)))
+            .order_by("total DESC, user_id")
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(rows.columns, vec!["user_id", "total", "sum"]);
+        assert_eq!(
+            rows.rows,
+            vec![
+                vec![Value::from(1), Value::from(2), Value::from(15)],
+                vec![Value::from(3), Value::from(1), Value::from(30)],
+            ]
+        );
+
+        // without GROUP BY the whole, possibly empty, table is one group
+        let rows = QueryBuilder::new("posts")
+            .aggregate(count("id"))
+            .aggregate(avg("likes"))
+            .filter(col("likes").gt(100))
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(rows.rows, vec![vec![Value::from(0), Value::Null]]);
+
+        let error = QueryBuilder::new("posts")
+            .filter(count("*").gt(1))
+            .fetch(&mut db)
+            .unwrap_err();
+        assert_eq!(error, ExecError::MisplacedAggregate);
+    }
+
+    #[test]
+    fn integer_overflow_is_an_error() {
+        let mut db = MemoryDatabase::new();
+        db.create_table("counters", &["hits"])
+            .insert("counters", vec![i64::MAX.into()])
+            .unwrap()
+            .insert("counters", vec![1.into()])
+            .unwrap();
+        for aggregate in [sum("hits"), avg("hits")] {
+            let error = QueryBuilder::new("counters")
+                .aggregate(aggregate)
+                .fetch(&mut db)
+                .unwrap_err();
+            assert_eq!(
+                error,
+                ExecError::Overflow(Value::Int(i64::MAX), Value::Int(1))
+            );
+        }
+    }
+
+    #[test]
+    fn subqueries_ctes_and_set_operations() {
+        let mut db = database();
+        let mut authors = QueryBuilder::new("posts");
+        authors.select("user_id");
+        let rows = QueryBuilder::new("users")
+            .select("name")
+            .filter(col("id").in_query(&authors))
+            .order_by("name")
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(texts(&rows, "name"), vec!["ann", "cid"]);
+
+        // correlated: users managing someone
+        let mut reports = QueryBuilder::new("users");
+        reports
+            .alias("r")
+            .filter(col("r.manager_id").eq(col("u.id")));
+        let rows = QueryBuilder::new("users")
+            .alias("u")
+            .select("name")
+            .filter(exists(&reports))
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(texts(&rows, "name"), vec!["ann", "cid"]);
+
+        let mut max_likes = QueryBuilder::new("posts");
+        max_likes.aggregate(max("likes"));
+        let rows = QueryBuilder::new("posts")
+            .select("title")
+            .filter(col("likes").eq(subquery(&max_likes)))
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(texts(&rows, "title"), vec!["rust"]);
+
+        // everybody below ann
+        let mut start = QueryBuilder::new("users");
+        start.select("id").filter(col("manager_id").eq(1));
+        let mut below = QueryBuilder::new("users");
+        below
+            .alias("u")
+            .select("u.id")
+            .inner_join("team", col("u.manager_id").eq(col("team.id")));
+        start.union_all(&below);
+        let rows = QueryBuilder::new("team")
+            .with_recursive("team", &start)
+            .order_by("id")
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(
+            rows.rows,
+            vec![
+                vec![Value::from(2)],
+                vec![Value::from(3)],
+                vec![Value::from(4)]
+            ]
+        );
+
+        let mut liked = QueryBuilder::new("posts");
+        liked.select("user_id").filter(col("likes").ge(10));
+        let rows = QueryBuilder::new("users")
+            .select("id")
+            .except(&liked)
+            .order_by("id DESC")
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(rows.rows, vec![vec![Value::from(4)], vec![Value::from(2)]]);
+    }
+
+    #[test]
+    fn same_query_renders_and_runs() {
+        fn newest(executor: &mut dyn Executor) -> Result<Rows, ExecError> {
+            UserQueryBuilder::new()
+                .select(users::username)
+                .filter(users::created_at.ge(100))
+                .order_by(users::created_at.desc())
+                .limit(1)
+                .fetch(executor)
+        }
+        let mut db = MemoryDatabase::new();
+        db.create_table("users", &["id", "username", "display_name", "created_at"]);
+        for (id, username, created_at) in [(1, "kim", 100), (2, "lee", 300), (3, "max", 50)] {
+            db.insert(
+                "users",
+                vec![id.into(), username.into(), Value::Null, created_at.into()],
+            )
+            .unwrap();
+        }
+        assert_eq!(texts(&newest(&mut db).unwrap(), "username"), vec!["lee"]);
+
+        let query = QueryBuilder::new("users").limit(-1).to_ast();
+        assert_eq!(
+            db.fetch(&query),
+            Err(ExecError::Build(BuildError::NegativeLimit(-1)))
+        );
+        assert_eq!(
+            query.render(&PostgreSQL),
+            Err(BuildError::NegativeLimit(-1))
+        );
+        assert_eq!(
+            QueryBuilder::new("comments").fetch(&mut db),
+            Err(ExecError::UnknownTable("comments".to_string()))
+        );
+        assert_eq!(
+            db.insert("users", vec![1.into()]).unwrap_err(),
+            ExecError::ColumnCountMismatch {
+                expected: 4,
+                found: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,460 @@
+// Sibling builders of QueryBuilder for the statements that change data.
+// Like QueryBuilder they fill an owned statement (`Insert`, `Update`, `Delete`) and share
+// the same writer, so values are bound as parameters and rendered per dialect.
+use std::marker::PhantomData;
+
+use super::{
+    and_condition, is_valid_identifier, split_list, BuildError, Condition, Dialect, Expr,
+    PostgreSQL, Query, SqlWriter, Value,
+};
+
+fn write_filter(filter: &Option<Condition>, w: &mut SqlWriter<'_>) {
+    if let Some(filter) = filter {
+        w.push(" WHERE ");
+        filter.write(w);
+    }
+}
+
+fn write_returning(returning: &[String], w: &mut SqlWriter<'_>) {
+    if returning.is_empty() {
+        return;
+    }
+    if !w.dialect.supports_returning() {
+        w.fail(BuildError::Unsupported {
+            dialect: w.dialect.name(),
+            feature: "RETURNING",
+        });
+    }
+    w.push(" RETURNING ");
+    let returning: Vec<&str> = returning.iter().map(String::as_str).collect();
+    w.push_ident_list(&returning);
+}
+
+pub(super) fn strings(list: &str) -> Vec<String> {
+    split_list(list).into_iter().map(String::from).collect()
+}
+
+// Conflict handling of an upsert, an empty `update` list keeps the existing row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upsert {
+    pub conflict: Vec<String>,
+    pub update: Vec<String>,
+}
+
+// INSERT INTO users (name, email) VALUES ($1, $2), ($3, $4)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Insert {
+    pub table: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    pub upsert: Option<Upsert>,
+    pub returning: Vec<String>,
+}
+
+impl Insert {
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            columns: vec![],
+            rows: vec![],
+            upsert: None,
+            returning: vec![],
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), BuildError> {
+        if self.table.trim().is_empty() {
+            return Err(BuildError::EmptyTableName);
+        }
+        if self.columns.is_empty() || self.rows.is_empty() {
+            return Err(BuildError::EmptyInsert);
+        }
+        if let Some(row) = self.rows.iter().find(|row| row.len() != self.columns.len()) {
+            return Err(BuildError::ColumnCountMismatch {
+                expected: self.columns.len(),
+                found: row.len(),
+            });
+        }
+        if matches!(&self.upsert, Some(upsert) if upsert.conflict.is_empty()) {
+            return Err(BuildError::MissingConflictTarget);
+        }
+        // the dialect quotes upsert columns itself, outside of the writer's checks, and a
+        // wildcard names no column to conflict on or update
+        if let Some(column) = self
+            .upsert
+            .iter()
+            .flat_map(|upsert| upsert.conflict.iter().chain(&upsert.update))
+            .find(|column| !is_valid_identifier(column) || column.ends_with('*'))
+        {
+            return Err(BuildError::InvalidIdentifier(column.clone()));
+        }
+        Ok(())
+    }
+
+    pub fn render(&self, dialect: &dyn Dialect) -> Result<Query, BuildError> {
+        self.validate()?;
+        let mut w = SqlWriter::new(dialect);
+        w.push("INSERT INTO ");
+        w.push_ident(&self.table);
+        w.push(" (");
+        let columns: Vec<&str> = self.columns.iter().map(String::as_str).collect();
+        w.push_ident_list(&columns);
+        w.push(") VALUES ");
+        for (i, row) in self.rows.iter().enumerate() {
+            if i > 0 {
+                w.push(", ");
+            }
+            w.push("(");
+            for (j, value) in row.iter().enumerate() {
+                if j > 0 {
+                    w.push(", ");
+                }
+                w.push_param(value.clone());
+            }
+            w.push(")");
+        }
+        if let Some(upsert) = &self.upsert {
+            let conflict: Vec<&str> = upsert.conflict.iter().map(String::as_str).collect();
+            let update: Vec<&str> = upsert.update.iter().map(String::as_str).collect();
+            w.push(&dialect.upsert(&conflict, &update));
+        }
+        write_returning(&self.returning, &mut w);
+        w.finish()
+    }
+}
+
+// UPDATE users SET name = $1 WHERE id = $2
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    pub table: String,
+    pub set: Vec<(String, Expr)>,
+    pub filter: Option<Condition>,
+    pub returning: Vec<String>,
+}
+
+impl Update {
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            set: vec![],
+            filter: None,
+            returning: vec![],
+        }
+    }
+
+    pub fn render(&self, dialect: &dyn Dialect) -> Result<Query, BuildError> {
+        if self.table.trim().is_empty() {
+            return Err(BuildError::EmptyTableName);
+        }
+        if self.set.is_empty() {
+            return Err(BuildError::EmptyUpdate);
+        }
+        let mut w = SqlWriter::new(dialect);
+        w.push("UPDATE ");
+        w.push_ident(&self.table);
+        w.push(" SET ");
+        for (i, (column, value)) in self.set.iter().enumerate() {
+            if i > 0 {
+                w.push(", ");
+            }
+            w.push_ident(column);
+            w.push(" = ");
+            value.write(&mut w);
+        }
+        write_filter(&self.filter, &mut w);
+        write_returning(&self.returning, &mut w);
+        w.finish()
+    }
+}
+
+// DELETE FROM users WHERE id = $1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delete {
+    pub table: String,
+    pub filter: Option<Condition>,
+    pub returning: Vec<String>,
+}
+
+impl Delete {
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            filter: None,
+            returning: vec![],
+        }
+    }
+
+    pub fn render(&self, dialect: &dyn Dialect) -> Result<Query, BuildError> {
+        if self.table.trim().is_empty() {
+            return Err(BuildError::EmptyTableName);
+        }
+        let mut w = SqlWriter::new(dialect);
+        w.push("DELETE FROM ");
+        w.push_ident(&self.table);
+        write_filter(&self.filter, &mut w);
+        write_returning(&self.returning, &mut w);
+        w.finish()
+    }
+}
+
+// Calling `values` several times inserts several rows in one statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertBuilder<'a> {
+    statement: Insert,
+    borrowed: PhantomData<&'a str>,
+}
+impl<'a> InsertBuilder<'a> {
+    pub fn new(table: &'a str) -> Self {
+        Self {
+            statement: Insert::new(table),
+            borrowed: PhantomData,
+        }
+    }
+    // Comma separated like `QueryBuilder::select`.
+    pub fn columns(&mut self, columns: &'a str) -> &mut Self {
+        self.statement.columns = strings(columns);
+        self
+    }
+    pub fn values(&mut self, row: impl IntoIterator<Item = Value>) -> &mut Self {
+        self.statement.rows.push(row.into_iter().collect());
+        self
+    }
+    fn upsert(&mut self) -> &mut Upsert {
+        self.statement.upsert.get_or_insert_with(|| Upsert {
+            conflict: vec![],
+            update: vec![],
+        })
+    }
+    // Unique columns a conflicting row is detected on, without `do_update` the existing
+    // row is kept.
+    pub fn on_conflict(&mut self, columns: &'a str) -> &mut Self {
+        self.upsert().conflict = strings(columns);
+        self
+    }
+    // Columns overwritten with the inserted values when the row already exists.
+    pub fn do_update(&mut self, columns: &'a str) -> &mut Self {
+        self.upsert().update = strings(columns);
+        self
+    }
+    pub fn returning(&mut self, columns: &'a str) -> &mut Self {
+        self.statement.returning = strings(columns);
+        self
+    }
+
+    pub fn to_ast(&self) -> Insert {
+        self.statement.clone()
+    }
+    pub fn build(&self) -> Result<Query, BuildError> {
+        self.build_with(&PostgreSQL)
+    }
+    pub fn build_with(&self, dialect: &dyn Dialect) -> Result<Query, BuildError> {
+        self.statement.render(dialect)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateBuilder<'a> {
+    statement: Update,
+    borrowed: PhantomData<&'a str>,
+}
+impl<'a> UpdateBuilder<'a> {
+    pub fn new(table: &'a str) -> Self {
+        Self {
+            statement: Update::new(table),
+            borrowed: PhantomData,
+        }
+    }
+    // The value can be another column: `set("updated_at", col("created_at"))`.
+    pub fn set(&mut self, column: &'a str, value: impl Into<Expr>) -> &mut Self {
+        self.statement.set.push((column.to_string(), value.into()));
+        self
+    }
+    pub fn filter(&mut self, condition: Condition) -> &mut Self {
+        and_condition(&mut self.statement.filter, condition);
+        self
+    }
+    pub fn returning(&mut self, columns: &'a str) -> &mut Self {
+        self.statement.returning = strings(columns);
+        self
+    }
+
+    pub fn to_ast(&self) -> Update {
+        self.statement.clone()
+    }
+    pub fn build(&self) -> Result<Query, BuildError> {
+        self.build_with(&PostgreSQL)
+    }
+    pub fn build_with(&self, dialect: &dyn Dialect) -> Result<Query, BuildError> {
+        self.statement.render(dialect)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteBuilder<'a> {
+    statement: Delete,
+    borrowed: PhantomData<&'a str>,
+}
+impl<'a> DeleteBuilder<'a> {
+    pub fn new(table: &'a str) -> Self {
+        Self {
+            statement: Delete::new(table),
+            borrowed: PhantomData,
+        }
+    }
+    pub fn filter(&mut self, condition: Condition) -> &mut Self {
+        and_condition(&mut self.statement.filter, condition);
+        self
+    }
+    pub fn returning(&mut self, columns: &'a str) -> &mut Self {
+        self.statement.returning = strings(columns);
+        self
+    }
+
+    pub fn to_ast(&self) -> Delete {
+        self.statement.clone()
+    }
+    pub fn build(&self) -> Result<Query, BuildError> {
+        self.build_with(&PostgreSQL)
+    }
+    pub fn build_with(&self, dialect: &dyn Dialect) -> Result<Query, BuildError> {
+        self.statement.render(dialect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn insert_multiple_rows_with_returning() {
+        let query = InsertBuilder::new("users")
+            .columns("name,email")
+            .values(["bob".into(), "bob@example.com".into()])
+            .values(["alice".into(), Value::Null])
+            .returning("id")
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"INSERT INTO "users" ("name", "email") VALUES ($1, $2), ($3, $4) RETURNING "id""#
+        );
+        assert_eq!(query.params.len(), 4);
+        assert_eq!(query.params[3], Value::Null);
+
+        let err = InsertBuilder::new("users")
+            .columns("name,email")
+            .values(["bob".into()])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BuildError::ColumnCountMismatch {
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(
+            InsertBuilder::new("users").columns("name").build(),
+            Err(BuildError::EmptyInsert)
+        );
+    }
+
+    #[test]
+    fn upsert_per_dialect() {
+        let mut insert = InsertBuilder::new("users");
+        insert
+            .columns("email,name")
+            .values(["bob@example.com".into(), "Bob".into()])
+            .on_conflict("email")
+            .do_update("name");
+        assert_eq!(
+            insert.build().unwrap().sql,
+            r#"INSERT INTO "users" ("email", "name") VALUES ($1, $2) ON CONFLICT ("email") DO UPDATE SET "name" = EXCLUDED."name""#
+        );
+        assert_eq!(
+            insert.build_with(&SQLite).unwrap().sql,
+            r#"INSERT INTO "users" ("email", "name") VALUES (?, ?) ON CONFLICT ("email") DO UPDATE SET "name" = EXCLUDED."name""#
+        );
+        assert_eq!(
+            insert.build_with(&MySQL).unwrap().sql,
+            "INSERT INTO `users` (`email`, `name`) VALUES (?, ?) AS `new` ON DUPLICATE KEY UPDATE `name` = `new`.`name`"
+        );
+
+        let query = InsertBuilder::new("tags")
+            .columns("name")
+            .values(["rust".into()])
+            .on_conflict("name")
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"INSERT INTO "tags" ("name") VALUES ($1) ON CONFLICT ("name") DO NOTHING"#
+        );
+
+        let err = InsertBuilder::new("tags")
+            .columns("name")
+            .values(["rust".into()])
+            .do_update("name")
+            .build()
+            .unwrap_err();
+        assert_eq!(err, BuildError::MissingConflictTarget);
+
+        let injected = "a\"; DROP TABLE x; --";
+        for (conflict, update, invalid) in [
+            (injected, "name", injected),
+            ("name", injected, injected),
+            ("*", "name", "*"),
+            ("name", "t.*", "t.*"),
+        ] {
+            let err = InsertBuilder::new("tags")
+                .columns("name")
+                .values(["rust".into()])
+                .on_conflict(conflict)
+                .do_update(update)
+                .build_with(&MySQL)
+                .unwrap_err();
+            assert_eq!(err, BuildError::InvalidIdentifier(invalid.to_string()));
+        }
+    }
+
+    #[test]
+    fn update_and_delete_with_where() {
+        let query = UpdateBuilder::new("users")
+            .set("name", "Bob")
+            .set("updated_at", col("created_at"))
+            .filter(col("id").eq(7))
+            .returning("id,name")
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"UPDATE "users" SET "name" = $1, "updated_at" = "created_at" WHERE "id" = $2 RETURNING "id", "name""#
+        );
+        assert_eq!(query.params, vec!["Bob".into(), 7.into()]);
+        assert_eq!(
+            UpdateBuilder::new("users").build(),
+            Err(BuildError::EmptyUpdate)
+        );
+
+        let query = DeleteBuilder::new("sessions")
+            .filter(col("expires_at").lt(100))
+            .build_with(&MySQL)
+            .unwrap();
+        assert_eq!(query.sql, "DELETE FROM `sessions` WHERE `expires_at` < ?");
+    }
+
+    #[test]
+    fn returning_is_rejected_by_mysql() {
+        let err = DeleteBuilder::new("sessions")
+            .returning("id")
+            .build_with(&MySQL)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BuildError::Unsupported {
+                dialect: "MySQL",
+                feature: "RETURNING"
+            }
+        );
+    }
+}
@@ -0,0 +1,1057 @@
+// SQL text back into a `Select`, for the SELECT subset the builder can render.
+// Accepts what any of the dialects produce (`"x"` or `` `x` `` quoting, `$1` or `?`
+// placeholders, `LIMIT n OFFSET m` or `LIMIT m, n`) as well as hand-written SQL with inline
+// literals, which become bound params once the parsed query is rendered again.
+//
+//   "SELECT ... FROM ..." --tokenize--> tokens --Parser (recursive descent)--> Select
+//
+// Rendering a parsed query gives back the SQL it was parsed from, up to whitespace,
+// keyword case and redundant parentheses.
+use std::fmt;
+
+use super::{
+    Aggregate, AggregateFn, Condition, Cte, Direction, Expr, Join, JoinKind, Operator, OrderBy,
+    Over, Query, QueryBuilder, Select, SelectItem, SetOperator, TableRef, Value, WindowFn,
+    WindowSpec,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    // a token other than the one the grammar allows here, `found` is empty at the end
+    Unexpected {
+        expected: &'static str,
+        found: String,
+    },
+    UnterminatedString,
+    UnterminatedIdentifier,
+    InvalidCharacter(char),
+    // `$n` or `?` without a value, plain `parse` binds no params at all
+    MissingParameter(usize),
+    // valid SQL the builder cannot represent, e.g. a column alias
+    Unsupported(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    // byte offset into the SQL text
+    pub position: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::Unexpected { expected, found } if found.is_empty() => {
+                write!(f, "expected {}, found end of input", expected)?
+            }
+            ParseErrorKind::Unexpected { expected, found } => {
+                write!(f, "expected {}, found `{}`", expected, found)?
+            }
+            ParseErrorKind::UnterminatedString => write!(f, "unterminated string literal")?,
+            ParseErrorKind::UnterminatedIdentifier => write!(f, "unterminated quoted identifier")?,
+            ParseErrorKind::InvalidCharacter(c) => write!(f, "unexpected character `{}`", c)?,
+            ParseErrorKind::MissingParameter(index) => {
+                write!(f, "no value bound for parameter {}", index)?
+            }
+            ParseErrorKind::Unsupported(feature) => write!(f, "{} is not supported", feature)?,
+        }
+        write!(f, " at position {}", self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// SQL with inline literals only, a placeholder is a MissingParameter error.
+pub fn parse(sql: &str) -> Result<Select, ParseError> {
+    Parser::new(sql, &[])?.statement()
+}
+
+// SQL rendered by any dialect, placeholders take their values from `query.params`.
+pub fn parse_query(query: &Query) -> Result<Select, ParseError> {
+    Parser::new(&query.sql, &query.params)?.statement()
+}
+
+impl QueryBuilder<'_> {
+    pub fn parse(sql: &str) -> Result<Self, ParseError> {
+        parse(sql).map(Self::from)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    // bare word, keywords included
+    Word(String),
+    Quoted(String),
+    Number(String),
+    Text(String),
+    // `$n`, or `?` which has no index
+    Placeholder(Option<usize>),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) | Token::Number(word) => write!(f, "{}", word),
+            Token::Quoted(ident) => write!(f, "\"{}\"", ident),
+            Token::Text(text) => write!(f, "'{}'", text),
+            Token::Placeholder(Some(index)) => write!(f, "${}", index),
+            Token::Placeholder(None) => write!(f, "?"),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 14] = [
+    "<>", "!=", "<=", ">=", "=", "<", ">", "(", ")", ",", ".", "*", "-", ";",
+];
+
+// Words that end an expression, they can never be a bare alias.
+pub(super) const RESERVED: [&str; 40] = [
+    "ALL",
+    "AND",
+    "AS",
+    "ASC",
+    "BETWEEN",
+    "BY",
+    "CROSS",
+    "DESC",
+    "DISTINCT",
+    "EXCEPT",
+    "EXISTS",
+    "FALSE",
+    "FROM",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "IN",
+    "INNER",
+    "INTERSECT",
+    "IS",
+    "JOIN",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "NOT",
+    "NULL",
+    "OFFSET",
+    "ON",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "OVER",
+    "PARTITION",
+    "RECURSIVE",
+    "RIGHT",
+    "SELECT",
+    "TRUE",
+    "UNION",
+    "WHERE",
+    "WINDOW",
+];
+
+pub(super) fn tokenize(sql: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars = sql.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let error = |kind| ParseError {
+            position: start,
+            kind,
+        };
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = if c.is_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_' || c == '$') {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            Token::Word(word)
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            Token::Number(number)
+        } else if c == '\'' || c == '"' || c == '`' {
+            // the closing quote is escaped by doubling it
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, q)) if q == c => {
+                        if chars.peek().map(|&(_, next)| next) == Some(c) {
+                            text.push(c);
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    Some((_, other)) => text.push(other),
+                    None if c == '\'' => return Err(error(ParseErrorKind::UnterminatedString)),
+                    None => return Err(error(ParseErrorKind::UnterminatedIdentifier)),
+                }
+            }
+            if c == '\'' {
+                Token::Text(text)
+            } else {
+                Token::Quoted(text)
+            }
+        } else if c == '?' {
+            chars.next();
+            Token::Placeholder(None)
+        } else if c == '$' {
+            chars.next();
+            let mut digits = String::new();
+            while let Some(&(_, c)) = chars.peek().filter(|(_, c)| c.is_ascii_digit()) {
+                digits.push(c);
+                chars.next();
+            }
+            match digits.parse() {
+                Ok(index) => Token::Placeholder(Some(index)),
+                Err(_) => return Err(error(ParseErrorKind::InvalidCharacter('$'))),
+            }
+        } else {
+            let rest = &sql[start..];
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or_else(|| error(ParseErrorKind::InvalidCharacter(c)))?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            Token::Symbol(symbol)
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+fn aggregate_fn(word: &str) -> Option<AggregateFn> {
+    [
+        AggregateFn::Count,
+        AggregateFn::Sum,
+        AggregateFn::Avg,
+        AggregateFn::Min,
+        AggregateFn::Max,
+    ]
+    .into_iter()
+    .find(|func| func.as_str().eq_ignore_ascii_case(word))
+}
+
+// Recursive descent over the token list, one method per grammar rule.
+struct Parser<'p> {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize,
+    params: &'p [Value],
+    // next value for a `?` placeholder
+    next_param: usize,
+}
+
+impl<'p> Parser<'p> {
+    fn new(sql: &str, params: &'p [Value]) -> Result<Self, ParseError> {
+        Ok(Self {
+            tokens: tokenize(sql)?,
+            pos: 0,
+            end: sql.len(),
+            params,
+            next_param: 0,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.peek_at(0)
+    }
+    fn peek_at(&self, ahead: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + ahead).map(|(_, token)| token)
+    }
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |(position, _)| *position)
+    }
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, token)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            position: self.position(),
+            kind,
+        }
+    }
+    fn unexpected(&self, expected: &'static str) -> ParseError {
+        self.error(ParseErrorKind::Unexpected {
+            expected,
+            found: self.peek().map(Token::to_string).unwrap_or_default(),
+        })
+    }
+
+    fn is_keyword_at(&self, ahead: usize, keyword: &str) -> bool {
+        matches!(self.peek_at(ahead), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+    fn is_keyword(&self, keyword: &str) -> bool {
+        self.is_keyword_at(0, keyword)
+    }
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+    fn expect_keyword(&mut self, keyword: &'static str) -> Result<(), ParseError> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(keyword))
+        }
+    }
+    fn is_symbol_at(&self, ahead: usize, symbol: &str) -> bool {
+        matches!(self.peek_at(ahead), Some(Token::Symbol(s)) if *s == symbol)
+    }
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let found = self.is_symbol_at(0, symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+    fn expect_symbol(&mut self, symbol: &'static str) -> Result<(), ParseError> {
+        if self.symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected(symbol))
+        }
+    }
+
+    // A whole input: one query, optionally followed by `;`.
+    fn statement(&mut self) -> Result<Select, ParseError> {
+        let query = self.query()?;
+        self.symbol(";");
+        match self.peek() {
+            None => Ok(query),
+            Some(_) => Err(self.unexpected("end of query")),
+        }
+    }
+
+    // [WITH ...] core {set operator core} [ORDER BY ...] [LIMIT ...] [OFFSET ...]
+    fn query(&mut self) -> Result<Select, ParseError> {
+        let ctes = if self.keyword("WITH") {
+            self.ctes()?
+        } else {
+            vec![]
+        };
+        let mut query = self.select_core()?;
+        query.ctes = ctes;
+        while let Some(operator) = self.set_operator() {
+            let operand = self.select_core()?;
+            query.set_operations.push((operator, operand));
+        }
+        if self.keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            query.order_by = self.list(Self::order_item)?;
+        }
+        if self.keyword("LIMIT") {
+            let first = self.integer()?;
+            if self.symbol(",") {
+                // MySQL: LIMIT offset, count
+                query.offset = first;
+                query.limit = self.integer()?;
+            } else {
+                query.limit = first;
+            }
+        }
+        if self.keyword("OFFSET") {
+            query.offset = self.integer()?;
+        }
+        Ok(query)
+    }
+
+    fn ctes(&mut self) -> Result<Vec<Cte>, ParseError> {
+        let recursive = self.keyword("RECURSIVE");
+        self.list(|parser| {
+            let name = parser.ident()?;
+            parser.expect_keyword("AS")?;
+            parser.expect_symbol("(")?;
+            let query = parser.query()?;
+            parser.expect_symbol(")")?;
+            Ok(Cte {
+                name,
+                query,
+                recursive,
+            })
+        })
+    }
+
+    fn set_operator(&mut self) -> Option<SetOperator> {
+        if self.keyword("UNION") {
+            if self.keyword("ALL") {
+                Some(SetOperator::UnionAll)
+            } else {
+                Some(SetOperator::Union)
+            }
+        } else if self.keyword("INTERSECT") {
+            Some(SetOperator::Intersect)
+        } else if self.keyword("EXCEPT") {
+            Some(SetOperator::Except)
+        } else {
+            None
+        }
+    }
+
+    // LIMIT and OFFSET values. The "no limit" spellings of MySQL (the largest unsigned
+    // 64-bit number) and SQLite (-1) mean None.
+    fn integer(&mut self) -> Result<Option<i64>, ParseError> {
+        let negative = self.symbol("-");
+        let position = self.position();
+        match self.advance() {
+            Some(Token::Number(number)) => match number.parse::<i64>() {
+                Ok(1) if negative => Ok(None),
+                Ok(value) if negative => Ok(Some(-value)),
+                Ok(value) => Ok(Some(value)),
+                Err(_) if number == u64::MAX.to_string() => Ok(None),
+                Err(_) => Err(ParseError {
+                    position,
+                    kind: ParseErrorKind::Unexpected {
+                        expected: "integer",
+                        found: number,
+                    },
+                }),
+            },
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("integer"))
+            }
+        }
+    }
+
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![item(self)?];
+        while self.symbol(",") {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    // SELECT [DISTINCT ON (...)] items FROM table {join} [WHERE ...] [GROUP BY ...]
+    // [HAVING ...] [WINDOW ...]
+    fn select_core(&mut self) -> Result<Select, ParseError> {
+        self.expect_keyword("SELECT")?;
+        let mut distinct_on = vec![];
+        if self.keyword("DISTINCT") {
+            if !self.keyword("ON") {
+                return Err(self.error(ParseErrorKind::Unsupported("DISTINCT without ON")));
+            }
+            self.expect_symbol("(")?;
+            distinct_on = self.list(Self::column)?;
+            self.expect_symbol(")")?;
+        }
+        let mut items = self.list(Self::select_item)?;
+        if items == [SelectItem::Column("*".to_string())] {
+            items.clear();
+        }
+        self.expect_keyword("FROM")?;
+        let mut query = Select::new(self.table_ref()?);
+        query.items = items;
+        query.distinct_on = distinct_on;
+        while let Some(join) = self.join()? {
+            query.joins.push(join);
+        }
+        if self.keyword("WHERE") {
+            query.filter = Some(self.condition()?);
+        }
+        if self.keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            query.group_by = self.list(Self::column)?;
+        }
+        if self.keyword("HAVING") {
+            query.having = Some(self.condition()?);
+        }
+        if self.keyword("WINDOW") {
+            query.windows = self.list(|parser| {
+                let name = parser.ident()?;
+                parser.expect_keyword("AS")?;
+                Ok((name, parser.window_spec()?))
+            })?;
+        }
+        Ok(query)
+    }
+
+    fn select_item(&mut self) -> Result<SelectItem, ParseError> {
+        if let Some(func) = self.window_fn()? {
+            let mut function = func.over(self.over()?);
+            if let Some(alias) = self.alias()? {
+                function = function.alias(&alias);
+            }
+            return Ok(SelectItem::Window(function));
+        }
+        let item = match self.aggregate()? {
+            Some((func, column)) if self.is_keyword("OVER") => {
+                let mut function = WindowFn::Aggregate(func, column).over(self.over()?);
+                if let Some(alias) = self.alias()? {
+                    function = function.alias(&alias);
+                }
+                return Ok(SelectItem::Window(function));
+            }
+            Some((func, column)) => {
+                let mut aggregate = Aggregate::new(func, &column);
+                if let Some(alias) = self.alias()? {
+                    aggregate = aggregate.alias(&alias);
+                }
+                return Ok(SelectItem::Aggregate(aggregate));
+            }
+            None => SelectItem::Column(self.column()?),
+        };
+        if self.is_keyword("AS") || self.is_alias() {
+            return Err(self.error(ParseErrorKind::Unsupported("column alias")));
+        }
+        Ok(item)
+    }
+
+    // FUNC(column) or FUNC(*)
+    fn aggregate(&mut self) -> Result<Option<(AggregateFn, String)>, ParseError> {
+        let func = match self.peek() {
+            Some(Token::Word(word)) if self.is_symbol_at(1, "(") => aggregate_fn(word),
+            _ => None,
+        };
+        let Some(func) = func else {
+            return Ok(None);
+        };
+        self.pos += 2;
+        let column = if self.symbol("*") {
+            "*".to_string()
+        } else {
+            self.column()?
+        };
+        self.expect_symbol(")")?;
+        Ok(Some((func, column)))
+    }
+
+    // ROW_NUMBER(), RANK(), DENSE_RANK(), LAG(column [, offset]), LEAD(column [, offset])
+    fn window_fn(&mut self) -> Result<Option<WindowFn>, ParseError> {
+        let name = match self.peek() {
+            Some(Token::Word(word)) if self.is_symbol_at(1, "(") => word.to_uppercase(),
+            _ => return Ok(None),
+        };
+        if !["ROW_NUMBER", "RANK", "DENSE_RANK", "LAG", "LEAD"].contains(&name.as_str()) {
+            return Ok(None);
+        }
+        self.pos += 2;
+        let func = match name.as_str() {
+            "ROW_NUMBER" => WindowFn::RowNumber,
+            "RANK" => WindowFn::Rank,
+            "DENSE_RANK" => WindowFn::DenseRank,
+            _ => {
+                let column = self.column()?;
+                let offset = if self.symbol(",") {
+                    let negative = self.symbol("-");
+                    let Value::Int(offset) = self.number(negative)? else {
+                        return Err(self.unexpected("integer"));
+                    };
+                    self.pos += 1;
+                    offset
+                } else {
+                    1
+                };
+                if name == "LAG" {
+                    WindowFn::Lag(column, offset)
+                } else {
+                    WindowFn::Lead(column, offset)
+                }
+            }
+        };
+        self.expect_symbol(")")?;
+        Ok(Some(func))
+    }
+
+    // OVER name | OVER (...)
+    fn over(&mut self) -> Result<Over, ParseError> {
+        self.expect_keyword("OVER")?;
+        if self.is_symbol_at(0, "(") {
+            Ok(Over::Spec(self.window_spec()?))
+        } else {
+            Ok(Over::Named(self.ident()?))
+        }
+    }
+
+    // ([PARTITION BY ...] [ORDER BY ...])
+    fn window_spec(&mut self) -> Result<WindowSpec, ParseError> {
+        self.expect_symbol("(")?;
+        let mut spec = WindowSpec::default();
+        if self.keyword("PARTITION") {
+            self.expect_keyword("BY")?;
+            spec.partition_by = self.list(Self::column)?;
+        }
+        if self.keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            spec.order_by = self.list(Self::order_item)?;
+        }
+        self.expect_symbol(")")?;
+        Ok(spec)
+    }
+
+    fn is_alias(&self) -> bool {
+        match self.peek() {
+            Some(Token::Quoted(_)) => true,
+            Some(Token::Word(word)) => !RESERVED.iter().any(|r| r.eq_ignore_ascii_case(word)),
+            _ => false,
+        }
+    }
+    // [AS] alias
+    fn alias(&mut self) -> Result<Option<String>, ParseError> {
+        if self.keyword("AS") || self.is_alias() {
+            self.ident().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Token::Quoted(ident)) => {
+                let ident = ident.clone();
+                self.pos += 1;
+                Ok(ident)
+            }
+            Some(Token::Word(word)) if self.is_alias() => {
+                let word = word.clone();
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(self.unexpected("identifier")),
+        }
+    }
+
+    // ident {. ident} [.*], kept in the builder's dotted form: `u.id`, `u.*`
+    fn column(&mut self) -> Result<String, ParseError> {
+        if self.symbol("*") {
+            return Ok("*".to_string());
+        }
+        let mut column = self.ident()?;
+        while self.symbol(".") {
+            column.push('.');
+            if self.symbol("*") {
+                column.push('*');
+                break;
+            }
+            column.push_str(&self.ident()?);
+        }
+        Ok(column)
+    }
+
+    fn table_ref(&mut self) -> Result<TableRef, ParseError> {
+        Ok(TableRef {
+            name: self.column()?,
+            alias: self.alias()?,
+        })
+    }
+
+    fn join(&mut self) -> Result<Option<Join>, ParseError> {
+        let kind = if self.keyword("CROSS") {
+            JoinKind::Cross
+        } else if self.keyword("INNER") || self.is_keyword("JOIN") {
+            JoinKind::Inner
+        } else if self.keyword("LEFT") {
+            JoinKind::Left
+        } else if self.keyword("RIGHT") {
+            JoinKind::Right
+        } else if self.keyword("FULL") {
+            JoinKind::Full
+        } else {
+            return Ok(None);
+        };
+        if matches!(kind, JoinKind::Left | JoinKind::Right | JoinKind::Full) {
+            self.keyword("OUTER");
+        }
+        self.expect_keyword("JOIN")?;
+        let table = self.table_ref()?;
+        let on = if kind == JoinKind::Cross {
+            None
+        } else {
+            self.expect_keyword("ON")?;
+            Some(self.condition()?)
+        };
+        Ok(Some(Join { kind, table, on }))
+    }
+
+    fn order_item(&mut self) -> Result<OrderBy, ParseError> {
+        let column = self.column()?;
+        let direction = if self.keyword("ASC") {
+            Some(Direction::Asc)
+        } else if self.keyword("DESC") {
+            Some(Direction::Desc)
+        } else {
+            None
+        };
+        Ok(OrderBy { column, direction })
+    }
+
+    // OR binds weaker than AND, AND weaker than NOT. Parentheses keep their grouping, so
+    // `a AND (b AND c)` stays a nested And like the builder would render it.
+    fn condition(&mut self) -> Result<Condition, ParseError> {
+        let first = self.conjunction()?;
+        if !self.is_keyword("OR") {
+            return Ok(first);
+        }
+        let mut conditions = vec![first];
+        while self.keyword("OR") {
+            conditions.push(self.conjunction()?);
+        }
+        Ok(Condition::Or(conditions))
+    }
+
+    fn conjunction(&mut self) -> Result<Condition, ParseError> {
+        let first = self.negation()?;
+        if !self.is_keyword("AND") {
+            return Ok(first);
+        }
+        let mut conditions = vec![first];
+        while self.keyword("AND") {
+            conditions.push(self.negation()?);
+        }
+        Ok(Condition::And(conditions))
+    }
+
+    fn negation(&mut self) -> Result<Condition, ParseError> {
+        if self.keyword("NOT") {
+            return Ok(!self.negation()?);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Condition, ParseError> {
+        if self.keyword("EXISTS") {
+            self.expect_symbol("(")?;
+            let query = self.query()?;
+            self.expect_symbol(")")?;
+            return Ok(Condition::Exists(Box::new(query)));
+        }
+        // constant conditions, rendered as TRUE/FALSE or 1/0 depending on the dialect:
+        // an empty AND is true, an empty OR false
+        let constant = match self.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("TRUE") => Some(true),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("FALSE") => Some(false),
+            Some(Token::Number(number)) if number == "1" => Some(true),
+            Some(Token::Number(number)) if number == "0" => Some(false),
+            _ => None,
+        };
+        if let Some(value) = constant.filter(|_| !self.is_predicate_at(1)) {
+            self.pos += 1;
+            return Ok(if value {
+                Condition::And(vec![])
+            } else {
+                Condition::Or(vec![])
+            });
+        }
+        if self.is_symbol_at(0, "(") && !self.is_query_at(1) {
+            if let Some(row) = self.row_compare()? {
+                return Ok(row);
+            }
+            self.expect_symbol("(")?;
+            let condition = self.condition()?;
+            self.expect_symbol(")")?;
+            return Ok(condition);
+        }
+        let left = self.expr()?;
+        self.predicate(left)
+    }
+
+    fn is_query_at(&self, ahead: usize) -> bool {
+        self.is_keyword_at(ahead, "SELECT") || self.is_keyword_at(ahead, "WITH")
+    }
+
+    fn is_predicate_at(&self, ahead: usize) -> bool {
+        ["=", "<>", "!=", "<", "<=", ">", ">="]
+            .iter()
+            .any(|symbol| self.is_symbol_at(ahead, symbol))
+            || ["NOT", "IN", "LIKE", "BETWEEN", "IS"]
+                .iter()
+                .any(|keyword| self.is_keyword_at(ahead, keyword))
+    }
+
+    // (a, b) > (x, y), backtracks when the parenthesis turns out to group a condition
+    fn row_compare(&mut self) -> Result<Option<Condition>, ParseError> {
+        let (start, next_param) = (self.pos, self.next_param);
+        let row = |parser: &mut Self| -> Result<Vec<Expr>, ParseError> {
+            parser.expect_symbol("(")?;
+            let exprs = parser.list(Self::expr)?;
+            parser.expect_symbol(")")?;
+            Ok(exprs)
+        };
+        let left = match row(self) {
+            Ok(left) if left.len() > 1 => left,
+            _ => {
+                self.pos = start;
+                self.next_param = next_param;
+                return Ok(None);
+            }
+        };
+        let op = self
+            .operator()
+            .ok_or_else(|| self.unexpected("comparison"))?;
+        Ok(Some(Condition::RowCompare(left, op, row(self)?)))
+    }
+
+    fn operator(&mut self) -> Option<Operator> {
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => Operator::Eq,
+            Some(Token::Symbol("<>" | "!=")) => Operator::Ne,
+            Some(Token::Symbol("<")) => Operator::Lt,
+            Some(Token::Symbol("<=")) => Operator::Le,
+            Some(Token::Symbol(">")) => Operator::Gt,
+            Some(Token::Symbol(">=")) => Operator::Ge,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(op)
+    }
+
+    // What follows the left side: `op expr`, `[NOT] IN (...)`, `[NOT] LIKE expr`,
+    // `[NOT] BETWEEN expr AND expr`, `IS [NOT] NULL`.
+    fn predicate(&mut self, left: Expr) -> Result<Condition, ParseError> {
+        if let Some(op) = self.operator() {
+            return Ok(Condition::Compare(left, op, self.expr()?));
+        }
+        if self.keyword("IS") {
+            let negated = self.keyword("NOT");
+            self.expect_keyword("NULL")?;
+            let condition = Condition::IsNull(left);
+            return Ok(if negated { !condition } else { condition });
+        }
+        let negated = self.keyword("NOT");
+        let condition = if self.keyword("IN") {
+            self.expect_symbol("(")?;
+            let condition = if self.is_query_at(0) {
+                Condition::InQuery(left, Box::new(self.query()?))
+            } else {
+                Condition::In(left, self.list(Self::expr)?)
+            };
+            self.expect_symbol(")")?;
+            condition
+        } else if self.keyword("LIKE") {
+            Condition::Like(left, self.expr()?)
+        } else if self.keyword("BETWEEN") {
+            let low = self.expr()?;
+            self.expect_keyword("AND")?;
+            Condition::Between(left, low, self.expr()?)
+        } else {
+            return Err(self.unexpected("comparison"));
+        };
+        Ok(if negated { !condition } else { condition })
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        if self.is_symbol_at(0, "(") && self.is_query_at(1) {
+            self.pos += 1;
+            let query = self.query()?;
+            self.expect_symbol(")")?;
+            return Ok(Expr::Subquery(Box::new(query)));
+        }
+        if let Some((func, column)) = self.aggregate()? {
+            return Ok(Expr::Aggregate(func, column));
+        }
+        let literal = match self.peek() {
+            Some(Token::Placeholder(index)) => {
+                let index = index.unwrap_or(self.next_param + 1);
+                let value = self
+                    .params
+                    .get(index.wrapping_sub(1))
+                    .cloned()
+                    .ok_or_else(|| self.error(ParseErrorKind::MissingParameter(index)))?;
+                self.next_param = index;
+                Some(value)
+            }
+            Some(Token::Text(text)) => Some(Value::Text(text.clone())),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("NULL") => Some(Value::Null),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("TRUE") => Some(true.into()),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("FALSE") => Some(false.into()),
+            Some(Token::Number(_)) => Some(self.number(false)?),
+            Some(Token::Symbol("-")) if matches!(self.peek_at(1), Some(Token::Number(_))) => {
+                self.pos += 1;
+                Some(self.number(true)?)
+            }
+            _ => None,
+        };
+        match literal {
+            Some(value) => {
+                self.pos += 1;
+                Ok(Expr::Param(value))
+            }
+            None => Ok(Expr::Column(self.column()?)),
+        }
+    }
+
+    // the Number token under the cursor, not consumed
+    fn number(&self, negative: bool) -> Result<Value, ParseError> {
+        let Some(Token::Number(number)) = self.peek() else {
+            return Err(self.unexpected("number"));
+        };
+        let text = if negative {
+            format!("-{}", number)
+        } else {
+            number.clone()
+        };
+        text.parse::<i64>()
+            .map(Value::Int)
+            .or_else(|_| text.parse::<f64>().map(Value::Float))
+            .map_err(|_| self.unexpected("number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    fn round_trip(builder: &QueryBuilder<'_>) {
+        let query = builder.to_ast();
+        for dialect in [&PostgreSQL as &dyn Dialect, &MySQL, &SQLite] {
+            let rendered = query.render(dialect).unwrap();
+            let parsed = parse_query(&rendered).unwrap();
+            assert_eq!(
+                parsed.render(dialect).unwrap(),
+                rendered,
+                "{}",
+                dialect.name()
+            );
+        }
+        let rendered = query.render(&PostgreSQL).unwrap();
+        assert_eq!(parse_query(&rendered).unwrap(), query);
+    }
+
+    #[test]
+    fn rendered_queries_round_trip() {
+        let mut active = QueryBuilder::new("users");
+        active.select("id").filter(col("active").eq(true));
+        let mut report = QueryBuilder::new("posts");
+        report
+            .alias("p")
+            .select("p.user_id")
+            .aggregate(count("*").alias("total"))
+            .aggregate(max("p.likes"))
+            .inner_join(table("users").alias("u"), col("u.id").eq(col("p.user_id")))
+            .left_join("tags", col("tags.post_id").eq(col("p.id")))
+            .filter(
+                col("p.title")
+                    .like("%rust%")
+                    .and(!col("p.id").is_in([1, 2, 3]))
+                    .and(
+                        col("p.deleted_at")
+                            .is_null()
+                            .or(col("p.score").between(1.5, -2)),
+                    ),
+            )
+            .filter(col("p.user_id").in_query(&active))
+            .group_by("p.user_id")
+            .having(count("*").gt(subquery(&active)))
+            .order_by("total DESC, p.user_id")
+            .limit(10)
+            .offset(20);
+        round_trip(&report);
+
+        let mut tree = QueryBuilder::new("nodes");
+        tree.select("id").filter(col("parent_id").is_null());
+        let mut children = QueryBuilder::new("nodes");
+        children
+            .alias("n")
+            .select("n.id")
+            .inner_join("tree", col("n.parent_id").eq(col("tree.id")));
+        tree.union_all(&children);
+        let mut archived = QueryBuilder::new("archive");
+        archived.select("id");
+        let mut everything = QueryBuilder::new("tree");
+        everything
+            .with_recursive("tree", &tree)
+            .select("id")
+            .filter(exists(&active).and(col("id").ne(Value::Null)))
+            .except(&archived)
+            .offset(5);
+        round_trip(&everything);
+
+        let mut page = QueryBuilder::new("events");
+        page.keyset(
+            "created_at DESC, id DESC",
+            Some(&Cursor::new(vec![7.into(), 3.into()])),
+            50,
+        );
+        round_trip(&page);
+    }
+
+    #[test]
+    fn parses_hand_written_sql() {
+        let parsed = QueryBuilder::parse(
+            "select u.id, count(*) as n from users u \
+             left outer join posts p on p.user_id = u.id \
+             where u.name != 'O''Brien' and not u.age < 18 and u.email is not null \
+             group by u.id order by n desc limit 5;",
+        )
+        .unwrap();
+        let query = parsed.build().unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "u"."id", COUNT(*) AS "n" FROM "users" AS "u" LEFT JOIN "posts" AS "p" ON "p"."user_id" = "u"."id" WHERE "u"."name" <> $1 AND NOT ("u"."age" < $2) AND NOT ("u"."email" IS NULL) GROUP BY "u"."id" ORDER BY "n" DESC LIMIT 5"#
+        );
+        assert_eq!(query.params, vec!["O'Brien".into(), 18.into()]);
+
+        // rewrite the parsed query like any other builder
+        let mut rewritten =
+            QueryBuilder::from(parse("SELECT * FROM logs WHERE level = 'error'").unwrap());
+        rewritten.filter(col("at").gt(1000)).limit(100);
+        assert_eq!(
+            rewritten.build().unwrap().sql,
+            r#"SELECT * FROM "logs" WHERE "level" = $1 AND "at" > $2 LIMIT 100"#
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_position() {
+        let error = parse("SELECT id FROM users WHERE").unwrap_err();
+        assert_eq!(error.position, 26);
+        assert_eq!(
+            error.to_string(),
+            "expected identifier, found end of input at position 26"
+        );
+        assert_eq!(
+            parse("SELECT id FROM users WHERE id = $1").unwrap_err(),
+            ParseError {
+                position: 32,
+                kind: ParseErrorKind::MissingParameter(1),
+            }
+        );
+        assert_eq!(
+            parse("SELECT id AS key FROM users").unwrap_err().kind,
+            ParseErrorKind::Unsupported("column alias")
+        );
+        assert_eq!(
+            parse("SELECT 'open FROM users").unwrap_err(),
+            ParseError {
+                position: 7,
+                kind: ParseErrorKind::UnterminatedString,
+            }
+        );
+        assert_eq!(
+            parse("SELECT id FROM users LIMIT 1 garbage")
+                .unwrap_err()
+                .kind,
+            ParseErrorKind::Unexpected {
+                expected: "end of query",
+                found: "garbage".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,320 @@
+// Typed table schemas.
+// `table!` declares a table once and generates a marker type per column plus a builder that
+// only accepts columns of that table with values of the column type, so a misspelled or
+// foreign column is a compile error instead of a SQL error at runtime:
+//
+//     table! { pub users => UserQueryBuilder { id: i64, created_at: i64 } }
+//     UserQueryBuilder::new().filter(users::id.eq(7)).order_by(users::created_at.desc());
+//
+// Column types are resolved inside the generated module, use absolute paths for your own
+// types.
+use std::marker::PhantomData;
+use std::ops::Not;
+
+use super::{
+    and_condition, table, BuildError, Condition, Dialect, Direction, ExecError, Executor, Expr,
+    Operator, OrderBy, PostgreSQL, Query, Rows, Select, SelectItem, Value,
+};
+
+pub trait Table {
+    const NAME: &'static str;
+}
+
+pub trait TypedColumn: Copy {
+    type Table: Table;
+    type Type: Into<Value>;
+    const NAME: &'static str;
+
+    fn eq(self, value: impl Into<Self::Type>) -> TypedCondition<Self::Table> {
+        compare::<Self>(Operator::Eq, value.into())
+    }
+    fn ne(self, value: impl Into<Self::Type>) -> TypedCondition<Self::Table> {
+        compare::<Self>(Operator::Ne, value.into())
+    }
+    fn lt(self, value: impl Into<Self::Type>) -> TypedCondition<Self::Table> {
+        compare::<Self>(Operator::Lt, value.into())
+    }
+    fn le(self, value: impl Into<Self::Type>) -> TypedCondition<Self::Table> {
+        compare::<Self>(Operator::Le, value.into())
+    }
+    fn gt(self, value: impl Into<Self::Type>) -> TypedCondition<Self::Table> {
+        compare::<Self>(Operator::Gt, value.into())
+    }
+    fn ge(self, value: impl Into<Self::Type>) -> TypedCondition<Self::Table> {
+        compare::<Self>(Operator::Ge, value.into())
+    }
+    fn is_in<V: Into<Self::Type>>(
+        self,
+        values: impl IntoIterator<Item = V>,
+    ) -> TypedCondition<Self::Table> {
+        let values = values
+            .into_iter()
+            .map(|value| Expr::Param(value.into().into()))
+            .collect();
+        TypedCondition::new(Condition::In(column::<Self>(), values))
+    }
+    fn like(self, pattern: impl Into<Self::Type>) -> TypedCondition<Self::Table> {
+        let pattern = Expr::Param(pattern.into().into());
+        TypedCondition::new(Condition::Like(column::<Self>(), pattern))
+    }
+    fn is_null(self) -> TypedCondition<Self::Table> {
+        TypedCondition::new(Condition::IsNull(column::<Self>()))
+    }
+    fn is_not_null(self) -> TypedCondition<Self::Table> {
+        !self.is_null()
+    }
+    fn between(
+        self,
+        low: impl Into<Self::Type>,
+        high: impl Into<Self::Type>,
+    ) -> TypedCondition<Self::Table> {
+        TypedCondition::new(Condition::Between(
+            column::<Self>(),
+            Expr::Param(low.into().into()),
+            Expr::Param(high.into().into()),
+        ))
+    }
+    fn asc(self) -> TypedOrder<Self::Table> {
+        order::<Self>(Direction::Asc)
+    }
+    fn desc(self) -> TypedOrder<Self::Table> {
+        order::<Self>(Direction::Desc)
+    }
+}
+
+fn column<C: TypedColumn>() -> Expr {
+    Expr::Column(C::NAME.to_string())
+}
+
+fn compare<C: TypedColumn>(op: Operator, value: C::Type) -> TypedCondition<C::Table> {
+    TypedCondition::new(Condition::Compare(
+        column::<C>(),
+        op,
+        Expr::Param(value.into()),
+    ))
+}
+
+fn order<C: TypedColumn>(direction: Direction) -> TypedOrder<C::Table> {
+    TypedOrder {
+        order: OrderBy {
+            column: C::NAME.to_string(),
+            direction: Some(direction),
+        },
+        table: PhantomData,
+    }
+}
+
+// A condition that only references columns of `T`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedCondition<T> {
+    condition: Condition,
+    table: PhantomData<T>,
+}
+
+impl<T> TypedCondition<T> {
+    fn new(condition: Condition) -> Self {
+        Self {
+            condition,
+            table: PhantomData,
+        }
+    }
+    pub fn and(self, other: TypedCondition<T>) -> Self {
+        Self::new(self.condition.and(other.condition))
+    }
+    pub fn or(self, other: TypedCondition<T>) -> Self {
+        Self::new(self.condition.or(other.condition))
+    }
+}
+
+impl<T> Not for TypedCondition<T> {
+    type Output = TypedCondition<T>;
+    fn not(self) -> Self::Output {
+        Self::new(!self.condition)
+    }
+}
+
+impl<T> From<TypedCondition<T>> for Condition {
+    fn from(condition: TypedCondition<T>) -> Self {
+        condition.condition
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedOrder<T> {
+    order: OrderBy,
+    table: PhantomData<T>,
+}
+
+/// The per-table builders `table!` generates are aliases of this type. A column of another
+/// table does not compile:
+///
+/// ```compile_fail
+/// use creational::builder::{posts, UserQueryBuilder};
+/// UserQueryBuilder::new().select(posts::title);
+/// ```
+///
+/// and neither does a column the table does not declare:
+///
+/// ```compile_fail
+/// use creational::builder::{users, TypedColumn, UserQueryBuilder};
+/// UserQueryBuilder::new().filter(users::email.eq("ada@example.com"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedQueryBuilder<T> {
+    query: Select,
+    table: PhantomData<T>,
+}
+
+impl<T: Table> TypedQueryBuilder<T> {
+    pub fn new() -> Self {
+        Self {
+            query: Select::new(table(T::NAME)),
+            table: PhantomData,
+        }
+    }
+    // Appends to the selected columns, nothing selected means `*`.
+    pub fn select<C: TypedColumn<Table = T>>(&mut self, _column: C) -> &mut Self {
+        self.query
+            .items
+            .push(SelectItem::Column(C::NAME.to_string()));
+        self
+    }
+    pub fn filter(&mut self, condition: TypedCondition<T>) -> &mut Self {
+        and_condition(&mut self.query.filter, condition.condition);
+        self
+    }
+    // Appends to the ordering.
+    pub fn order_by(&mut self, order: TypedOrder<T>) -> &mut Self {
+        self.query.order_by.push(order.order);
+        self
+    }
+    pub fn limit(&mut self, limit: i64) -> &mut Self {
+        self.query.limit = Some(limit);
+        self
+    }
+    pub fn offset(&mut self, offset: i64) -> &mut Self {
+        self.query.offset = Some(offset);
+        self
+    }
+
+    // Untyped copy of the query, e.g. to continue with `QueryBuilder::from`.
+    pub fn to_ast(&self) -> Select {
+        self.query.clone()
+    }
+    pub fn build(&self) -> Result<Query, BuildError> {
+        self.build_with(&PostgreSQL)
+    }
+    pub fn build_with(&self, dialect: &dyn Dialect) -> Result<Query, BuildError> {
+        self.query.render(dialect)
+    }
+    pub fn fetch(&self, executor: &mut dyn Executor) -> Result<Rows, ExecError> {
+        executor.fetch(&self.query)
+    }
+}
+
+impl<T: Table> Default for TypedQueryBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[macro_export]
+macro_rules! table {
+    ($(#[$meta:meta])* $vis:vis $table:ident => $builder:ident { $($column:ident : $ty:ty),+ $(,)? }) => {
+        $(#[$meta])*
+        #[allow(non_camel_case_types)]
+        $vis mod $table {
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub struct Table;
+            impl $crate::builder::Table for Table {
+                const NAME: &'static str = stringify!($table);
+            }
+            $(
+                #[derive(Debug, Clone, Copy)]
+                pub struct $column;
+                impl $crate::builder::TypedColumn for $column {
+                    type Table = Table;
+                    type Type = $ty;
+                    const NAME: &'static str = stringify!($column);
+                }
+            )+
+        }
+        $vis type $builder = $crate::builder::TypedQueryBuilder<$table::Table>;
+    };
+}
+
+// The tables of the diagram above QueryBuilder.
+table! {
+    pub users => UserQueryBuilder {
+        id: i64,
+        username: String,
+        display_name: String,
+        created_at: i64,
+    }
+}
+table! {
+    pub posts => PostQueryBuilder {
+        id: i64,
+        user_id: i64,
+        title: String,
+        published: bool,
+        created_at: i64,
+    }
+}
+table! {
+    pub tags => TagQueryBuilder {
+        id: i64,
+        post_id: i64,
+        name: String,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn typed_builders_render_like_query_builder() {
+        let query = UserQueryBuilder::new()
+            .select(users::id)
+            .select(users::username)
+            .filter(users::username.like("k%").and(!users::id.is_in([1, 2])))
+            .order_by(users::created_at.desc())
+            .limit(10)
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "id", "username" FROM "users" WHERE "username" LIKE $1 AND NOT ("id" IN ($2, $3)) ORDER BY "created_at" DESC LIMIT 10"#
+        );
+        assert_eq!(query.params, vec!["k%".into(), 1.into(), 2.into()]);
+
+        let untyped = QueryBuilder::new("posts")
+            .filter(col("published").eq(true))
+            .filter(col("user_id").between(1, 9))
+            .build_with(&MySQL)
+            .unwrap();
+        let typed = PostQueryBuilder::new()
+            .filter(posts::published.eq(true))
+            .filter(posts::user_id.between(1, 9))
+            .build_with(&MySQL)
+            .unwrap();
+        assert_eq!(typed, untyped);
+    }
+
+    #[test]
+    fn typed_query_converts_to_untyped_builder() {
+        let query = QueryBuilder::from(
+            TagQueryBuilder::new()
+                .filter(tags::name.eq("rust"))
+                .to_ast(),
+        )
+        .aggregate(count("*"))
+        .build()
+        .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT COUNT(*) FROM "tags" WHERE "name" = $1"#
+        );
+    }
+}
@@ -0,0 +1,353 @@
+// Owned representation of a SELECT statement.
+// QueryBuilder only fills a `Select`, which can then be stored, cloned, sent to another
+// thread, inspected or rewritten field by field before it is rendered for a dialect.
+use super::{
+    is_valid_identifier, write_order_by, Aggregate, BuildError, Condition, Cte, Dialect, Join,
+    Over, Query, SetOperator, SqlWriter, TableRef, WindowFn, WindowFunction, WindowSpec,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectItem {
+    // a plain, optionally qualified, column: `id`, `u.id`, `u.*`
+    Column(String),
+    Aggregate(Aggregate),
+    Window(WindowFunction),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Asc,
+    Desc,
+}
+
+impl Direction {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Asc => "ASC",
+            Direction::Desc => "DESC",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBy {
+    pub column: String,
+    pub direction: Option<Direction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Select {
+    pub ctes: Vec<Cte>,
+    // PostgreSQL only: keep the first row of every distinct combination of these columns
+    pub distinct_on: Vec<String>,
+    // an empty list selects `*`
+    pub items: Vec<SelectItem>,
+    pub from: TableRef,
+    pub joins: Vec<Join>,
+    pub filter: Option<Condition>,
+    pub group_by: Vec<String>,
+    pub having: Option<Condition>,
+    // WINDOW clause, named windows referenced by `Over::Named`
+    pub windows: Vec<(String, WindowSpec)>,
+    pub set_operations: Vec<(SetOperator, Select)>,
+    // ORDER BY, LIMIT and OFFSET apply to the combined result of the set operations
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl Select {
+    pub fn new(from: TableRef) -> Self {
+        Self {
+            ctes: vec![],
+            distinct_on: vec![],
+            items: vec![],
+            from,
+            joins: vec![],
+            filter: None,
+            group_by: vec![],
+            having: None,
+            windows: vec![],
+            set_operations: vec![],
+            order_by: vec![],
+            limit: None,
+            offset: None,
+        }
+    }
+
+    // Clauses are always rendered in SQL order: WITH, SELECT, FROM, JOIN, WHERE, GROUP BY,
+    // HAVING, set operations, ORDER BY, LIMIT/OFFSET.
+    pub fn render(&self, dialect: &dyn Dialect) -> Result<Query, BuildError> {
+        let mut w = SqlWriter::new(dialect);
+        self.write(&mut w);
+        w.finish()
+    }
+
+    fn selected_columns(&self) -> Vec<&str> {
+        if self.items.is_empty() {
+            return vec!["*"];
+        }
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                SelectItem::Column(column) => Some(column.as_str()),
+                SelectItem::Aggregate(_) | SelectItem::Window(_) => None,
+            })
+            .collect()
+    }
+    pub(super) fn is_grouped(&self) -> bool {
+        !self.group_by.is_empty()
+            || self.having.is_some()
+            || self
+                .items
+                .iter()
+                .any(|item| matches!(item, SelectItem::Aggregate(_)))
+    }
+
+    // Once a query groups rows, every plain selected column must be a grouping column,
+    // otherwise the database cannot pick a single value per group.
+    fn validate_grouping(&self) -> Result<(), BuildError> {
+        if !self.is_grouped() {
+            return Ok(());
+        }
+        match self
+            .selected_columns()
+            .into_iter()
+            .find(|column| !self.group_by.iter().any(|group| group == column))
+        {
+            Some(column) => Err(BuildError::UngroupedColumn(column.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), BuildError> {
+        if self.from.name.trim().is_empty() {
+            return Err(BuildError::EmptyTableName);
+        }
+        if let Some(limit) = self.limit.filter(|limit| *limit < 0) {
+            return Err(BuildError::NegativeLimit(limit));
+        }
+        if let Some(offset) = self.offset.filter(|offset| *offset < 0) {
+            return Err(BuildError::NegativeOffset(offset));
+        }
+        // the builder keeps an unparsable ORDER BY item whole in `column`
+        if let Some(order) = self
+            .order_by
+            .iter()
+            .find(|order| !is_valid_identifier(&order.column))
+        {
+            return Err(BuildError::InvalidOrderBy(order.column.clone()));
+        }
+        for (_, query) in &self.set_operations {
+            query.validate_set_operand()?;
+            query.validate()?;
+        }
+        self.validate_windows()?;
+        self.validate_distinct_on()?;
+        self.validate_grouping()
+    }
+
+    fn validate_windows(&self) -> Result<(), BuildError> {
+        for (_, spec) in &self.windows {
+            spec.validate()?;
+        }
+        for item in &self.items {
+            let SelectItem::Window(WindowFunction { func, over, .. }) = item else {
+                continue;
+            };
+            if let WindowFn::Lag(_, offset) | WindowFn::Lead(_, offset) = func {
+                if *offset < 0 {
+                    return Err(BuildError::NegativeWindowOffset(*offset));
+                }
+            }
+            match over {
+                Over::Named(name) if !self.windows.iter().any(|(window, _)| window == name) => {
+                    return Err(BuildError::UnknownWindow(name.clone()));
+                }
+                Over::Spec(spec) => spec.validate()?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // The database picks the first row of each DISTINCT ON group in ORDER BY order, so the
+    // ordering has to start with the DISTINCT ON columns.
+    fn validate_distinct_on(&self) -> Result<(), BuildError> {
+        match self
+            .order_by
+            .iter()
+            .take(self.distinct_on.len())
+            .find(|order| !self.distinct_on.contains(&order.column))
+        {
+            Some(order) => Err(BuildError::DistinctOnOrderBy(order.column.clone())),
+            None => Ok(()),
+        }
+    }
+
+    // Operands are plain SELECTs, anything that needs parentheses is not portable.
+    fn validate_set_operand(&self) -> Result<(), BuildError> {
+        let clause = if !self.order_by.is_empty() {
+            "ORDER BY"
+        } else if self.limit.is_some() || self.offset.is_some() {
+            "LIMIT/OFFSET"
+        } else if !self.ctes.is_empty() {
+            "WITH"
+        } else if !self.set_operations.is_empty() {
+            "nested set operation"
+        } else {
+            return Ok(());
+        };
+        Err(BuildError::InvalidSetOperand(clause))
+    }
+
+    // Also used for subqueries, errors are collected by the writer.
+    pub(super) fn write(&self, w: &mut SqlWriter<'_>) {
+        if let Err(error) = self.validate() {
+            w.fail(error);
+        }
+        if !self.ctes.is_empty() {
+            w.push("WITH ");
+            if self.ctes.iter().any(|cte| cte.recursive) {
+                w.push("RECURSIVE ");
+            }
+            for (i, cte) in self.ctes.iter().enumerate() {
+                if i > 0 {
+                    w.push(", ");
+                }
+                w.push_ident(&cte.name);
+                w.push(" AS (");
+                cte.query.write(w);
+                w.push(")");
+            }
+            w.push(" ");
+        }
+        self.write_select(w);
+        for (operator, query) in &self.set_operations {
+            w.push(" ");
+            w.push(operator.as_str());
+            w.push(" ");
+            query.write_select(w);
+        }
+        if !self.order_by.is_empty() {
+            w.push(" ORDER BY ");
+            write_order_by(&self.order_by, w);
+        }
+        let limit_offset = w.dialect.limit_offset(self.limit, self.offset);
+        w.push(&limit_offset);
+    }
+
+    fn write_select(&self, w: &mut SqlWriter<'_>) {
+        w.push("SELECT ");
+        if !self.distinct_on.is_empty() {
+            if !w.dialect.supports_distinct_on() {
+                w.fail(BuildError::Unsupported {
+                    dialect: w.dialect.name(),
+                    feature: "DISTINCT ON",
+                });
+            }
+            w.push("DISTINCT ON (");
+            let columns: Vec<&str> = self.distinct_on.iter().map(String::as_str).collect();
+            w.push_ident_list(&columns);
+            w.push(") ");
+        }
+        if self.items.is_empty() {
+            w.push("*");
+        }
+        for (i, item) in self.items.iter().enumerate() {
+            if i > 0 {
+                w.push(", ");
+            }
+            match item {
+                SelectItem::Column(column) => w.push_ident(column),
+                SelectItem::Aggregate(aggregate) => aggregate.write(w),
+                SelectItem::Window(function) => function.write(w),
+            }
+        }
+        w.push(" FROM ");
+        self.from.write(w);
+        for join in &self.joins {
+            join.write(w);
+        }
+        if let Some(filter) = &self.filter {
+            w.push(" WHERE ");
+            filter.write(w);
+        }
+        if !self.group_by.is_empty() {
+            w.push(" GROUP BY ");
+            let group_by: Vec<&str> = self.group_by.iter().map(String::as_str).collect();
+            w.push_ident_list(&group_by);
+        }
+        if let Some(having) = &self.having {
+            w.push(" HAVING ");
+            having.write(w);
+        }
+        if !self.windows.is_empty() {
+            w.push(" WINDOW ");
+            for (i, (name, spec)) in self.windows.iter().enumerate() {
+                if i > 0 {
+                    w.push(", ");
+                }
+                w.push_ident(name);
+                w.push(" AS ");
+                spec.write(w);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    fn assert_owned<T: Clone + Send + Sync + 'static>() {}
+
+    #[test]
+    fn ast_is_owned_and_shareable() {
+        assert_owned::<Select>();
+        assert_owned::<Query>();
+    }
+
+    #[test]
+    fn ast_outlives_the_strings_it_was_built_from() {
+        let query = {
+            let table = String::from("users");
+            let min_age = String::from("age");
+            QueryBuilder::new(&table)
+                .select("id")
+                .filter(col(&min_age).ge(18))
+                .to_ast()
+        };
+        let handle = std::thread::spawn(move || query.render(&PostgreSQL));
+        let rendered = handle.join().unwrap().unwrap();
+        assert_eq!(
+            rendered.sql,
+            r#"SELECT "id" FROM "users" WHERE "age" >= $1"#
+        );
+    }
+
+    #[test]
+    fn ast_can_be_inspected_and_rewritten() {
+        let mut query = QueryBuilder::new("users")
+            .select("id,name")
+            .order_by("name")
+            .to_ast();
+        assert_eq!(
+            query.items,
+            vec![
+                SelectItem::Column("id".into()),
+                SelectItem::Column("name".into())
+            ]
+        );
+        query.from = table("archived_users").alias("u");
+        query.limit = Some(5);
+        query.order_by[0].direction = Some(Direction::Desc);
+        assert_eq!(
+            query.render(&SQLite).unwrap().sql,
+            r#"SELECT "id", "name" FROM "archived_users" AS "u" ORDER BY "name" DESC LIMIT 5"#
+        );
+        // and handed back to the builder
+        let query = QueryBuilder::from(query).offset(10).build().unwrap();
+        assert!(query.sql.ends_with("LIMIT 5 OFFSET 10"));
+    }
+}
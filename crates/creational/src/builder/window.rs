@@ -0,0 +1,441 @@
+// Window functions: `row_number().over(window().partition_by("user_id").order_by("at"))`.
+// Unlike an aggregate, a window function keeps every row and adds a value computed over
+// the rows of its partition, e.g. a rank, the previous value or a running sum:
+//
+//   SUM("amount") OVER (PARTITION BY "account" ORDER BY "day") AS "balance"
+//
+// A window can be defined once in the WINDOW clause (`QueryBuilder::named_window`) and
+// referenced by name from several functions: `lag("price", 1).over("w")`.
+use super::{parse_order_by, split_list, Aggregate, AggregateFn, BuildError, OrderBy, SqlWriter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowFn {
+    RowNumber,
+    Rank,
+    DenseRank,
+    // value of the column `offset` rows before / after the current one
+    Lag(String, i64),
+    Lead(String, i64),
+    // running aggregate: SUM(x) OVER (ORDER BY ...) sums up to the current row
+    Aggregate(AggregateFn, String),
+}
+
+impl WindowFn {
+    pub(super) fn name(&self) -> &'static str {
+        match self {
+            WindowFn::RowNumber => "ROW_NUMBER",
+            WindowFn::Rank => "RANK",
+            WindowFn::DenseRank => "DENSE_RANK",
+            WindowFn::Lag(..) => "LAG",
+            WindowFn::Lead(..) => "LEAD",
+            WindowFn::Aggregate(func, _) => func.as_str(),
+        }
+    }
+
+    pub fn over(self, over: impl Into<Over>) -> WindowFunction {
+        WindowFunction {
+            func: self,
+            over: over.into(),
+            alias: None,
+        }
+    }
+
+    fn write(&self, w: &mut SqlWriter<'_>) {
+        w.push(self.name());
+        w.push("(");
+        match self {
+            WindowFn::RowNumber | WindowFn::Rank | WindowFn::DenseRank => {}
+            WindowFn::Lag(column, offset) | WindowFn::Lead(column, offset) => {
+                w.push_ident(column);
+                w.push(&format!(", {}", offset));
+            }
+            WindowFn::Aggregate(_, column) => w.push_ident(column),
+        }
+        w.push(")");
+    }
+}
+
+pub fn row_number() -> WindowFn {
+    WindowFn::RowNumber
+}
+pub fn rank() -> WindowFn {
+    WindowFn::Rank
+}
+pub fn dense_rank() -> WindowFn {
+    WindowFn::DenseRank
+}
+pub fn lag(column: &str, offset: i64) -> WindowFn {
+    WindowFn::Lag(column.to_string(), offset)
+}
+pub fn lead(column: &str, offset: i64) -> WindowFn {
+    WindowFn::Lead(column.to_string(), offset)
+}
+
+impl Aggregate {
+    // The aggregate's alias names the window function.
+    pub fn over(self, over: impl Into<Over>) -> WindowFunction {
+        WindowFunction {
+            alias: self.alias,
+            ..WindowFn::Aggregate(self.func, self.column).over(over)
+        }
+    }
+}
+
+// PARTITION BY ... ORDER BY ..., both optional: `OVER ()` is the whole result.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WindowSpec {
+    pub partition_by: Vec<String>,
+    pub order_by: Vec<OrderBy>,
+}
+
+pub fn window() -> WindowSpec {
+    WindowSpec::default()
+}
+
+impl WindowSpec {
+    // Comma separated columns.
+    pub fn partition_by(self, columns: &str) -> Self {
+        Self {
+            partition_by: split_list(columns).into_iter().map(String::from).collect(),
+            ..self
+        }
+    }
+    // Comma separated `column [ASC|DESC]` items, like `QueryBuilder::order_by`.
+    pub fn order_by(self, order_by: &str) -> Self {
+        Self {
+            order_by: split_list(order_by)
+                .into_iter()
+                .map(parse_order_by)
+                .collect(),
+            ..self
+        }
+    }
+
+    pub(super) fn validate(&self) -> Result<(), BuildError> {
+        match self
+            .order_by
+            .iter()
+            .find(|order| !super::is_valid_identifier(&order.column))
+        {
+            Some(order) => Err(BuildError::InvalidOrderBy(order.column.clone())),
+            None => Ok(()),
+        }
+    }
+
+    pub(super) fn write(&self, w: &mut SqlWriter<'_>) {
+        w.push("(");
+        if !self.partition_by.is_empty() {
+            w.push("PARTITION BY ");
+            let columns: Vec<&str> = self.partition_by.iter().map(String::as_str).collect();
+            w.push_ident_list(&columns);
+        }
+        if !self.order_by.is_empty() {
+            if !self.partition_by.is_empty() {
+                w.push(" ");
+            }
+            w.push("ORDER BY ");
+            super::write_order_by(&self.order_by, w);
+        }
+        w.push(")");
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Over {
+    // a window of the WINDOW clause
+    Named(String),
+    Spec(WindowSpec),
+}
+
+impl From<WindowSpec> for Over {
+    fn from(spec: WindowSpec) -> Self {
+        Over::Spec(spec)
+    }
+}
+impl From<&str> for Over {
+    fn from(name: &str) -> Self {
+        Over::Named(name.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowFunction {
+    pub func: WindowFn,
+    pub over: Over,
+    pub alias: Option<String>,
+}
+
+impl WindowFunction {
+    pub fn alias(self, alias: &str) -> Self {
+        Self {
+            alias: Some(alias.to_string()),
+            ..self
+        }
+    }
+
+    pub(super) fn write(&self, w: &mut SqlWriter<'_>) {
+        if !w.dialect.supports_window_functions() {
+            w.fail(BuildError::Unsupported {
+                dialect: w.dialect.name(),
+                feature: "window functions",
+            });
+        }
+        self.func.write(w);
+        w.push(" OVER ");
+        match &self.over {
+            Over::Named(name) => w.push_ident(name),
+            Over::Spec(spec) => spec.write(w),
+        }
+        if let Some(alias) = &self.alias {
+            w.push(" AS ");
+            w.push_ident(alias);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    fn sales() -> MemoryDatabase {
+        let mut db = MemoryDatabase::new();
+        db.create_table("sales", &["id", "region", "day", "amount"]);
+        let sales = [
+            (1, "east", 1, 10),
+            (2, "east", 2, 30),
+            (3, "east", 2, 20),
+            (4, "west", 1, 5),
+            (5, "west", 3, 15),
+        ];
+        for (id, region, day, amount) in sales {
+            db.insert(
+                "sales",
+                vec![id.into(), region.into(), day.into(), amount.into()],
+            )
+            .unwrap();
+        }
+        db
+    }
+
+    fn ints(rows: &Rows, column: &str) -> Vec<Option<i64>> {
+        rows.column(column)
+            .unwrap()
+            .into_iter()
+            .map(|value| match value {
+                Value::Int(value) => Some(*value),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renders_window_functions_and_named_windows() {
+        let by_day = window().partition_by("region").order_by("day");
+        let query = QueryBuilder::new("sales")
+            .select("id")
+            .window(row_number().over(by_day.clone()).alias("n"))
+            .window(sum("amount").alias("balance").over("w"))
+            .window(lag("amount", 1).over("w"))
+            .named_window("w", by_day)
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.sql,
+            r#"SELECT "id", ROW_NUMBER() OVER (PARTITION BY "region" ORDER BY "day") AS "n", SUM("amount") OVER "w" AS "balance", LAG("amount", 1) OVER "w" FROM "sales" WINDOW "w" AS (PARTITION BY "region" ORDER BY "day")"#
+        );
+        let all = QueryBuilder::new("sales")
+            .window(rank().over(window()))
+            .build_with(&MySQL)
+            .unwrap();
+        assert_eq!(all.sql, "SELECT RANK() OVER () FROM `sales`");
+
+        let unknown = QueryBuilder::new("sales")
+            .window(dense_rank().over("w"))
+            .build();
+        assert_eq!(unknown, Err(BuildError::UnknownWindow("w".to_string())));
+    }
+
+    #[test]
+    fn window_functions_need_dialect_support() {
+        struct Legacy;
+        impl Dialect for Legacy {
+            fn name(&self) -> &'static str {
+                "Legacy"
+            }
+            fn quote_identifier(&self, ident: &str) -> String {
+                ident.to_string()
+            }
+            fn placeholder(&self, _index: usize) -> String {
+                "?".to_string()
+            }
+            fn boolean(&self, value: bool) -> &'static str {
+                if value {
+                    "1"
+                } else {
+                    "0"
+                }
+            }
+            fn limit_offset(&self, _limit: Option<i64>, _offset: Option<i64>) -> String {
+                String::new()
+            }
+            fn supports_window_functions(&self) -> bool {
+                false
+            }
+        }
+        let query = QueryBuilder::new("sales")
+            .window(row_number().over(window()))
+            .build_with(&Legacy);
+        assert_eq!(
+            query,
+            Err(BuildError::Unsupported {
+                dialect: "Legacy",
+                feature: "window functions",
+            })
+        );
+    }
+
+    #[test]
+    fn distinct_on_is_postgres_only_and_checks_order_by() {
+        let mut latest = QueryBuilder::new("sales");
+        latest
+            .select("region, amount")
+            .distinct_on("region")
+            .order_by("region, day DESC");
+        assert_eq!(
+            latest.build().unwrap().sql,
+            r#"SELECT DISTINCT ON ("region") "region", "amount" FROM "sales" ORDER BY "region", "day" DESC"#
+        );
+        for dialect in [&MySQL as &dyn Dialect, &SQLite] {
+            assert_eq!(
+                latest.build_with(dialect),
+                Err(BuildError::Unsupported {
+                    dialect: dialect.name(),
+                    feature: "DISTINCT ON",
+                })
+            );
+        }
+        let unordered = QueryBuilder::new("sales")
+            .distinct_on("region")
+            .order_by("day")
+            .build();
+        assert_eq!(
+            unordered,
+            Err(BuildError::DistinctOnOrderBy("day".to_string()))
+        );
+    }
+
+    #[test]
+    fn memory_database_evaluates_windows() {
+        let mut db = sales();
+        let rows = QueryBuilder::new("sales")
+            .select("id")
+            .window(row_number().over("w").alias("n"))
+            .window(rank().over("w").alias("rank"))
+            .window(dense_rank().over("w").alias("dense"))
+            .window(sum("amount").alias("running").over("w"))
+            .window(
+                sum("amount")
+                    .alias("total")
+                    .over(window().partition_by("region")),
+            )
+            .window(lag("amount", 1).over("w").alias("previous"))
+            .window(lead("amount", 1).over("w").alias("next"))
+            .named_window("w", window().partition_by("region").order_by("day"))
+            .order_by("id")
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(ints(&rows, "n"), [1, 2, 3, 1, 2].map(Some));
+        assert_eq!(ints(&rows, "rank"), [1, 2, 2, 1, 2].map(Some));
+        assert_eq!(ints(&rows, "dense"), [1, 2, 2, 1, 2].map(Some));
+        // days 2 are peers, the running sum includes both
+        assert_eq!(ints(&rows, "running"), [10, 60, 60, 5, 20].map(Some));
+        assert_eq!(ints(&rows, "total"), [60, 60, 60, 20, 20].map(Some));
+        assert_eq!(
+            ints(&rows, "previous"),
+            [None, Some(10), Some(30), None, Some(5)]
+        );
+        assert_eq!(
+            ints(&rows, "next"),
+            [Some(30), Some(20), None, Some(15), None]
+        );
+    }
+
+    #[test]
+    fn lag_and_lead_offsets_are_bounded() {
+        let mut db = sales();
+        let negative = QueryBuilder::new("sales")
+            .window(lag("amount", i64::MIN).over(window()))
+            .fetch(&mut db);
+        assert_eq!(
+            negative,
+            Err(ExecError::Build(BuildError::NegativeWindowOffset(i64::MIN)))
+        );
+
+        // past the end of the partition, and of i64, is NULL
+        let rows = QueryBuilder::new("sales")
+            .select("id")
+            .window(lag("amount", i64::MAX).over("w").alias("before"))
+            .window(lead("amount", i64::MAX).over("w").alias("after"))
+            .named_window("w", window().order_by("id"))
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(ints(&rows, "before"), [None; 5]);
+        assert_eq!(ints(&rows, "after"), [None; 5]);
+    }
+
+    #[test]
+    fn memory_database_keeps_first_row_per_distinct_on_group() {
+        let mut db = sales();
+        let rows = QueryBuilder::new("sales")
+            .select("region, amount")
+            .distinct_on("region")
+            .order_by("region, amount DESC")
+            .fetch(&mut db)
+            .unwrap();
+        assert_eq!(ints(&rows, "amount"), [Some(30), Some(15)]);
+
+        let grouped = QueryBuilder::new("sales")
+            .select("region")
+            .window(row_number().over(window()))
+            .aggregate(count("*"))
+            .group_by("region")
+            .fetch(&mut db);
+        assert_eq!(
+            grouped,
+            Err(ExecError::Unsupported("window functions over grouped rows"))
+        );
+    }
+
+    #[test]
+    fn parses_windows_and_distinct_on() {
+        let mut query = QueryBuilder::new("sales");
+        query
+            .select("region, day")
+            .distinct_on("region")
+            .window(lead("amount", 2).over("w").alias("later"))
+            .window(max("amount").over(window().order_by("day DESC")))
+            .named_window("w", window().partition_by("region"))
+            .order_by("region");
+        let ast = query.to_ast();
+        let sql = ast.render(&PostgreSQL).unwrap();
+        assert_eq!(parse_query(&sql).unwrap(), ast);
+
+        let hand_written = parse(
+            "select id, rank() over (order by amount desc) as r, lag(amount) over w \
+             from sales window w as (partition by region order by day)",
+        )
+        .unwrap();
+        assert_eq!(
+            hand_written.items[2],
+            SelectItem::Window(lag("amount", 1).over("w"))
+        );
+        assert_eq!(
+            parse("SELECT DISTINCT id FROM sales").unwrap_err().kind,
+            ParseErrorKind::Unsupported("DISTINCT without ON")
+        );
+        assert_eq!(
+            fingerprint("select row_number() over (partition by region) from sales").shape,
+            r#"SELECT ROW_NUMBER() OVER (PARTITION BY "region") FROM "sales""#
+        );
+    }
+}
@@ -46,44 +46,129 @@
 //                             └───────────────────────────────────────────────────────────────────────────────
 //
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Window {
-    pub background: String,
-    pub text: String,
+    pub background: Color,
+    pub text: Color,
     pub widget_name: String,
+    // name of the theme that created it, see `Window::check_theme`
+    pub theme: String,
+    pub widgets: Vec<Widget>,
 }
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct ScrollBar {
     pub width: i64,
     pub height: i64,
     pub widget_name: String,
+    pub theme: String,
+    // how far the content is scrolled in percent, 0 at the start and 100 at the end, kept
+    // when the theme changes
+    pub position: i64,
 }
 #[derive(Debug, Clone)]
 pub struct ThemeInfo {
     pub name: String,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum Themes {
-    PMWindow,
-    Motif,
-}
+mod color;
+mod inheritance;
+mod layout;
+mod manager;
+mod registry;
+mod render;
+mod theme_file;
+mod widgets;
+pub use color::{Color, ContrastReport, ParseColorError, WcagLevel};
+pub use inheritance::{Property, PropertyValue, ResolvedTheme, ResolvedValue, ThemeOverrides};
+pub use layout::{Align, Direction, Layout, LayoutError, NodeId, Rect};
+pub use manager::{Restyle, ThemeChange, ThemeManager};
+pub use registry::{ThemeError, ThemeRegistry};
+pub use render::{Canvas, Cell};
+pub use theme_file::{ThemeFile, ThemeFileError, ThemeFileErrorKind};
+pub use widgets::{Button, Dialog, Label, Menu, TextBox, ThemeMismatch, Widget};
 
 pub mod abstract_factory {
     use super::*;
+    // Only the window, scroll bar and theme info are required, the other widgets default
+    // to the window's colors: buttons inverted, labels in the text color.
     pub trait WidgetFactory {
         fn create_window(&self) -> Window;
         fn create_scroll_bar(&self) -> ScrollBar;
         fn get_theme_info(&self) -> ThemeInfo;
+
+        fn create_button(&self, label: &str) -> Button {
+            let (window, theme) = (self.create_window(), self.get_theme_info().name);
+            Button {
+                label: label.to_string(),
+                background: window.text,
+                text: window.background,
+                border_width: 1,
+                widget_name: format!("{}Button", theme),
+                theme,
+            }
+        }
+        fn create_menu(&self, items: &[&str]) -> Menu {
+            let (window, theme) = (self.create_window(), self.get_theme_info().name);
+            Menu {
+                items: items.iter().map(|item| item.to_string()).collect(),
+                background: window.background,
+                text: window.text,
+                highlight: window.text,
+                widget_name: format!("{}Menu", theme),
+                theme,
+            }
+        }
+        fn create_text_box(&self) -> TextBox {
+            let (window, theme) = (self.create_window(), self.get_theme_info().name);
+            TextBox {
+                value: String::new(),
+                background: window.background,
+                text: window.text,
+                border_width: 1,
+                width: 20,
+                widget_name: format!("{}TextBox", theme),
+                theme,
+            }
+        }
+        fn create_label(&self, content: &str) -> Label {
+            let (window, theme) = (self.create_window(), self.get_theme_info().name);
+            Label {
+                content: content.to_string(),
+                text: window.text,
+                widget_name: format!("{}Label", theme),
+                theme,
+            }
+        }
+        // One button per label, created by `create_button` so they share the dialog's theme.
+        fn create_dialog(&self, title: &str, buttons: &[&str]) -> Dialog {
+            let (window, theme) = (self.create_window(), self.get_theme_info().name);
+            Dialog {
+                title: title.to_string(),
+                background: window.background,
+                text: window.text,
+                buttons: buttons
+                    .iter()
+                    .map(|label| self.create_button(label))
+                    .collect(),
+                widget_name: format!("{}Dialog", theme),
+                theme,
+            }
+        }
     }
 
+    // Flat, light widgets with thin outlines.
     pub struct PMWindowWidget {}
+    impl PMWindowWidget {
+        const THEME: &'static str = "PMWindow Widget";
+    }
     impl WidgetFactory for PMWindowWidget {
         fn create_window(&self) -> Window {
             Window {
-                background: "white".into(),
-                text: "green".into(),
+                background: Color::WHITE,
+                text: Color::GREEN,
                 widget_name: "PMWindow".to_string(),
+                theme: Self::THEME.to_string(),
+                widgets: vec![],
             }
         }
 
@@ -92,23 +177,84 @@ pub mod abstract_factory {
                 width: 300,
                 height: 300,
                 widget_name: "ScrollBar".to_string(),
+                theme: Self::THEME.to_string(),
+                position: 0,
             }
         }
 
         fn get_theme_info(&self) -> ThemeInfo {
             ThemeInfo {
-                name: "PMWindow Widget".into(),
+                name: Self::THEME.into(),
+            }
+        }
+
+        fn create_button(&self, label: &str) -> Button {
+            Button {
+                label: label.to_string(),
+                background: Color::hex(0xc0c0c0),
+                text: Color::BLACK,
+                border_width: 1,
+                widget_name: "PMButton".to_string(),
+                theme: Self::THEME.to_string(),
+            }
+        }
+        fn create_menu(&self, items: &[&str]) -> Menu {
+            Menu {
+                items: items.iter().map(|item| item.to_string()).collect(),
+                background: Color::WHITE,
+                text: Color::BLACK,
+                highlight: Color::hex(0x000080),
+                widget_name: "PMMenu".to_string(),
+                theme: Self::THEME.to_string(),
+            }
+        }
+        fn create_text_box(&self) -> TextBox {
+            TextBox {
+                value: String::new(),
+                background: Color::WHITE,
+                text: Color::BLACK,
+                border_width: 1,
+                width: 20,
+                widget_name: "PMTextBox".to_string(),
+                theme: Self::THEME.to_string(),
+            }
+        }
+        fn create_label(&self, content: &str) -> Label {
+            Label {
+                content: content.to_string(),
+                text: Color::GREEN,
+                widget_name: "PMLabel".to_string(),
+                theme: Self::THEME.to_string(),
+            }
+        }
+        fn create_dialog(&self, title: &str, buttons: &[&str]) -> Dialog {
+            Dialog {
+                title: title.to_string(),
+                background: Color::WHITE,
+                text: Color::GREEN,
+                buttons: buttons
+                    .iter()
+                    .map(|label| self.create_button(label))
+                    .collect(),
+                widget_name: "PMDialog".to_string(),
+                theme: Self::THEME.to_string(),
             }
         }
     }
 
+    // Dark widgets with beveled, 3D borders.
     pub struct MotifWidget {}
+    impl MotifWidget {
+        const THEME: &'static str = "Motif Widget";
+    }
     impl WidgetFactory for MotifWidget {
         fn create_window(&self) -> Window {
             Window {
-                background: "green".into(),
-                text: "white".into(),
+                background: Color::GREEN,
+                text: Color::WHITE,
                 widget_name: "MotifScollbar".to_string(),
+                theme: Self::THEME.to_string(),
+                widgets: vec![],
             }
         }
 
@@ -117,19 +263,67 @@ pub mod abstract_factory {
                 width: 200,
                 height: 200,
                 widget_name: "MotifScollbar".to_string(),
+                theme: Self::THEME.to_string(),
+                position: 0,
             }
         }
         fn get_theme_info(&self) -> ThemeInfo {
             ThemeInfo {
-                name: "Motif Widget".into(),
+                name: Self::THEME.into(),
             }
         }
-    }
-    // https://doc.rust-lang.org/rust-by-example/trait/dyn.htm
-    pub fn get_widget_factory(theme: Themes) -> Box<dyn WidgetFactory> {
-        match theme {
-            Themes::PMWindow => Box::new(PMWindowWidget {}),
-            Themes::Motif => Box::new(MotifWidget {}),
+
+        fn create_button(&self, label: &str) -> Button {
+            Button {
+                label: label.to_string(),
+                background: Color::hex(0x708090),
+                text: Color::WHITE,
+                border_width: 2,
+                widget_name: "MotifButton".to_string(),
+                theme: Self::THEME.to_string(),
+            }
+        }
+        fn create_menu(&self, items: &[&str]) -> Menu {
+            Menu {
+                items: items.iter().map(|item| item.to_string()).collect(),
+                background: Color::GREEN,
+                text: Color::WHITE,
+                highlight: Color::hex(0x006400),
+                widget_name: "MotifMenu".to_string(),
+                theme: Self::THEME.to_string(),
+            }
+        }
+        fn create_text_box(&self) -> TextBox {
+            TextBox {
+                value: String::new(),
+                background: Color::hex(0xf5f5f5),
+                text: Color::BLACK,
+                border_width: 2,
+                width: 24,
+                widget_name: "MotifTextBox".to_string(),
+                theme: Self::THEME.to_string(),
+            }
+        }
+        fn create_label(&self, content: &str) -> Label {
+            Label {
+                content: content.to_string(),
+                text: Color::WHITE,
+                widget_name: "MotifLabel".to_string(),
+                theme: Self::THEME.to_string(),
+            }
+        }
+        fn create_dialog(&self, title: &str, buttons: &[&str]) -> Dialog {
+            Dialog {
+                title: title.to_string(),
+                background: Color::GREEN,
+                text: Color::WHITE,
+                buttons: buttons
+                    .iter()
+                    .map(|label| self.create_button(label))
+                    .collect(),
+                widget_name: "MotifDialog".to_string(),
+                theme: Self::THEME.to_string(),
+            }
         }
     }
 }
@@ -138,23 +332,23 @@ pub mod abstract_factory {
 // Define an interface for creating an object, but let subclasses decide which class to instantiate.
 // Facfoty method lets a class defer instantiation to subclasses.
 
+// Every theme has its own product types, so the compiler keeps a family together: a
+// `MotifWindow` only takes a `MotifScrollBar`. `WidgetFamily::into_dyn` turns a family into
+// the `Box<dyn WidgetFactory>` of the abstract factory when the theme is picked at runtime.
 pub mod factory_method {
+    use super::abstract_factory::{MotifWidget, PMWindowWidget, WidgetFactory};
     use super::*;
-    trait WidgetFactoryMethod {
+
+    pub trait WidgetFactoryMethod {
         type Object;
         fn create() -> Box<Self::Object>
         where
             Self::Object: Default,
         {
-            Box::new(Default::default())
+            Box::default()
         }
     }
 
-    trait WidgetFactory {
-        fn create_scroll_bar(&self) -> Box<ScrollBar>;
-        fn create_window(&self) -> Box<Window>;
-    }
-
     impl WidgetFactoryMethod for Window {
         type Object = Window;
         fn create() -> Box<Self::Object> {
@@ -177,24 +371,198 @@ pub mod factory_method {
         }
     }
 
-    pub struct PMWidgetFactory {}
-    impl WidgetFactory for PMWidgetFactory {
-        fn create_scroll_bar(&self) -> Box<ScrollBar> {
-            ScrollBar::create()
+    /// A window of one family, it only accepts that family's scroll bars:
+    ///
+    /// ```compile_fail
+    /// use creational::factory::factory_method::*;
+    /// let mut window = MotifWidgetFactory {}.create_window();
+    /// window.add_scroll_bar(PMWidgetFactory {}.create_scroll_bar());
+    /// ```
+    ///
+    /// nor through the window it wraps:
+    ///
+    /// ```compile_fail
+    /// use creational::factory::{factory_method::*, ScrollBar};
+    /// let mut window = MotifWidgetFactory {}.create_window();
+    /// window.0.add(ScrollBar::from(PMWidgetFactory {}.create_scroll_bar()));
+    /// ```
+    pub trait FamilyWindow: Into<Window> {
+        type ScrollBar: Into<ScrollBar>;
+        fn add_scroll_bar(&mut self, scroll_bar: Self::ScrollBar) -> &mut Self;
+    }
+
+    pub trait WidgetFamily {
+        type Window: FamilyWindow<ScrollBar = Self::ScrollBar>;
+        type ScrollBar: Into<ScrollBar>;
+        // The abstract factory the family is styled like, it makes the other widgets.
+        type Theme: WidgetFactory;
+        fn create_window(&self) -> Self::Window;
+        fn create_scroll_bar(&self) -> Self::ScrollBar;
+        fn theme(&self) -> Self::Theme;
+        fn get_theme_info(&self) -> ThemeInfo {
+            self.theme().get_theme_info()
         }
 
-        fn create_window(&self) -> Box<Window> {
-            Window::create()
+        fn into_dyn(self) -> Box<dyn WidgetFactory>
+        where
+            Self: Sized + 'static,
+        {
+            Box::new(Dynamic(self))
         }
     }
-    pub struct MotifWidgetFactory {}
-    impl WidgetFactory for MotifWidgetFactory {
-        fn create_scroll_bar(&self) -> Box<ScrollBar> {
-            ScrollBar::create()
+
+    // The products of a family, type-erased.
+    struct Dynamic<F>(F);
+    impl<F: WidgetFamily> WidgetFactory for Dynamic<F> {
+        fn create_window(&self) -> Window {
+            self.0.create_window().into()
+        }
+        fn create_scroll_bar(&self) -> ScrollBar {
+            self.0.create_scroll_bar().into()
+        }
+        fn get_theme_info(&self) -> ThemeInfo {
+            self.0.get_theme_info()
+        }
+        fn create_button(&self, label: &str) -> Button {
+            self.0.theme().create_button(label)
+        }
+        fn create_menu(&self, items: &[&str]) -> Menu {
+            self.0.theme().create_menu(items)
+        }
+        fn create_text_box(&self) -> TextBox {
+            self.0.theme().create_text_box()
+        }
+        fn create_label(&self, content: &str) -> Label {
+            self.0.theme().create_label(content)
         }
+        fn create_dialog(&self, title: &str, buttons: &[&str]) -> Dialog {
+            self.0.theme().create_dialog(title, buttons)
+        }
+    }
+
+    // Products styled like the abstract factory's theme of the same name.
+    macro_rules! family {
+        ($factory:ident, $window:ident, $scroll_bar:ident, $theme:ident) => {
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            pub struct $window(Window);
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            pub struct $scroll_bar(ScrollBar);
 
-        fn create_window(&self) -> Box<Window> {
-            Window::create()
+            // Read only, a `&mut Window` would take scroll bars of any family.
+            impl AsRef<Window> for $window {
+                fn as_ref(&self) -> &Window {
+                    &self.0
+                }
+            }
+            impl AsRef<ScrollBar> for $scroll_bar {
+                fn as_ref(&self) -> &ScrollBar {
+                    &self.0
+                }
+            }
+
+            impl From<$window> for Window {
+                fn from(window: $window) -> Self {
+                    window.0
+                }
+            }
+            impl From<$scroll_bar> for ScrollBar {
+                fn from(scroll_bar: $scroll_bar) -> Self {
+                    scroll_bar.0
+                }
+            }
+            impl FamilyWindow for $window {
+                type ScrollBar = $scroll_bar;
+                fn add_scroll_bar(&mut self, scroll_bar: $scroll_bar) -> &mut Self {
+                    self.0.add(scroll_bar.0);
+                    self
+                }
+            }
+            impl Default for $window {
+                fn default() -> Self {
+                    $window($theme {}.create_window())
+                }
+            }
+            impl Default for $scroll_bar {
+                fn default() -> Self {
+                    $scroll_bar($theme {}.create_scroll_bar())
+                }
+            }
+            impl WidgetFactoryMethod for $window {
+                type Object = $window;
+            }
+            impl WidgetFactoryMethod for $scroll_bar {
+                type Object = $scroll_bar;
+            }
+
+            pub struct $factory {}
+            impl WidgetFamily for $factory {
+                type Window = $window;
+                type ScrollBar = $scroll_bar;
+                type Theme = $theme;
+                fn create_window(&self) -> $window {
+                    *$window::create()
+                }
+                fn create_scroll_bar(&self) -> $scroll_bar {
+                    *$scroll_bar::create()
+                }
+                fn theme(&self) -> $theme {
+                    $theme {}
+                }
+            }
+        };
+    }
+    family!(PMWidgetFactory, PMWindow, PMScrollBar, PMWindowWidget);
+    family!(MotifWidgetFactory, MotifWindow, MotifScrollBar, MotifWidget);
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // Only compiles with a window and scroll bar of the same family.
+        fn window_with_scroll_bar<F: WidgetFamily>(factory: &F) -> Window {
+            let mut window = factory.create_window();
+            window.add_scroll_bar(factory.create_scroll_bar());
+            window.into()
+        }
+
+        #[test]
+        fn each_family_has_its_own_products() {
+            let pm = window_with_scroll_bar(&PMWidgetFactory {});
+            let motif = window_with_scroll_bar(&MotifWidgetFactory {});
+            assert_eq!(pm.background, Color::WHITE);
+            assert_eq!(motif.background, Color::GREEN);
+            assert_eq!(pm.check_theme(), Ok(()));
+            assert_eq!(motif.widgets.len(), 1);
+            assert_eq!(motif.widgets[0].theme(), "Motif Widget");
+            let scroll_bar = MotifWidgetFactory {}.create_scroll_bar();
+            let scroll_bar: &ScrollBar = scroll_bar.as_ref();
+            assert_eq!(scroll_bar.widget_name, "MotifScollbar");
+
+            assert_eq!(Window::create().widget_name, "Window");
+            assert_eq!(ScrollBar::create().widget_name, "ScrollBar");
+        }
+
+        #[test]
+        fn families_convert_to_the_dynamic_factory() {
+            let families = [
+                PMWidgetFactory {}.into_dyn(),
+                MotifWidgetFactory {}.into_dyn(),
+            ];
+            let themes: [Box<dyn WidgetFactory>; 2] =
+                [Box::new(PMWindowWidget {}), Box::new(MotifWidget {})];
+            for (factory, theme) in families.iter().zip(themes) {
+                assert_eq!(factory.create_window(), theme.create_window());
+                assert_eq!(factory.create_scroll_bar(), theme.create_scroll_bar());
+                assert_eq!(factory.get_theme_info().name, theme.get_theme_info().name);
+                assert_eq!(factory.create_button("OK"), theme.create_button("OK"));
+                assert_eq!(factory.create_menu(&["File"]), theme.create_menu(&["File"]));
+                assert_eq!(factory.create_text_box(), theme.create_text_box());
+                assert_eq!(factory.create_label("Name"), theme.create_label("Name"));
+                assert_eq!(
+                    factory.create_dialog("Quit?", &["Yes", "No"]),
+                    theme.create_dialog("Quit?", &["Yes", "No"])
+                );
+            }
         }
     }
 }
@@ -0,0 +1,424 @@
+// Theme colors, parsed from the notations designers write in CSS:
+//
+//   "teal"  "#008080"  "#088"  "rgb(0, 128, 128)"  "rgb(0% 50% 50%)"
+//
+// and checked for readability: WCAG 2 asks for a contrast ratio of at least 4.5:1 between
+// text and its background (level AA), 7:1 for level AAA.
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError(pub String);
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is not a color, expected a CSS name, #rgb, #rrggbb or rgb(r, g, b)",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseColorError {}
+
+impl Color {
+    pub const BLACK: Color = Color::hex(0x000000);
+    pub const WHITE: Color = Color::hex(0xffffff);
+    pub const GREEN: Color = Color::hex(0x008000);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+    // 0xrrggbb
+    pub const fn hex(rgb: u32) -> Self {
+        Self::rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+    // CSS color keyword, case-insensitive.
+    pub fn named(name: &str) -> Option<Self> {
+        NAMES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, rgb)| Self::hex(*rgb))
+    }
+
+    // Relative luminance as defined by WCAG 2, 0.0 for black and 1.0 for white.
+    pub fn luminance(&self) -> f64 {
+        let linear = |channel: u8| {
+            let c = f64::from(channel) / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+    // From 1.0 (same luminance) to 21.0 (black on white), the order of the colors does
+    // not matter.
+    pub fn contrast_ratio(&self, other: &Color) -> f64 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || ParseColorError(text.to_string());
+        let trimmed = text.trim();
+        if let Some(hex) = trimmed.strip_prefix('#') {
+            if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(error());
+            }
+            let value = u32::from_str_radix(hex, 16).map_err(|_| error())?;
+            return match hex.len() {
+                6 => Ok(Self::hex(value)),
+                // #abc is #aabbcc
+                3 => {
+                    let digit = |shift: u32| ((value >> shift) & 0xf) as u8 * 0x11;
+                    Ok(Self::rgb(digit(8), digit(4), digit(0)))
+                }
+                _ => Err(error()),
+            };
+        }
+        let lower = trimmed.to_ascii_lowercase();
+        if let Some(arguments) = lower
+            .strip_prefix("rgb(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let channels: Vec<&str> = arguments
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|channel| !channel.is_empty())
+                .collect();
+            let channels = channels
+                .iter()
+                .map(|channel| parse_channel(channel))
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(error)?;
+            return match channels[..] {
+                [r, g, b] => Ok(Self::rgb(r, g, b)),
+                _ => Err(error()),
+            };
+        }
+        Self::named(trimmed).ok_or_else(error)
+    }
+}
+
+// `0` to `255`, or `0%` to `100%`.
+fn parse_channel(channel: &str) -> Option<u8> {
+    match channel.strip_suffix('%') {
+        Some(percent) => {
+            let percent: f64 = percent.parse().ok()?;
+            (0.0..=100.0)
+                .contains(&percent)
+                .then(|| (percent * 255.0 / 100.0).round() as u8)
+        }
+        None => channel.parse().ok(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WcagLevel {
+    AA,
+    AAA,
+}
+
+impl WcagLevel {
+    // Minimum ratio for normal-size text.
+    pub fn min_ratio(&self) -> f64 {
+        match self {
+            WcagLevel::AA => 4.5,
+            WcagLevel::AAA => 7.0,
+        }
+    }
+}
+
+// Contrast between the text and background of a theme's windows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastReport {
+    pub theme: String,
+    pub text: Color,
+    pub background: Color,
+    pub ratio: f64,
+}
+
+impl ContrastReport {
+    pub fn new(theme: &str, text: Color, background: Color) -> Self {
+        Self {
+            theme: theme.to_string(),
+            text,
+            background,
+            ratio: text.contrast_ratio(&background),
+        }
+    }
+    pub fn passes(&self, level: WcagLevel) -> bool {
+        self.ratio >= level.min_ratio()
+    }
+    // The highest level met, None when the text fails even AA.
+    pub fn level(&self) -> Option<WcagLevel> {
+        [WcagLevel::AAA, WcagLevel::AA]
+            .into_iter()
+            .find(|level| self.passes(*level))
+    }
+}
+
+impl fmt::Display for ContrastReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level() {
+            Some(WcagLevel::AAA) => "AAA",
+            Some(WcagLevel::AA) => "AA",
+            None => "fails AA",
+        };
+        write!(
+            f,
+            "{}: {} on {} has a contrast of {:.2}:1 ({})",
+            self.theme, self.text, self.background, self.ratio, level
+        )
+    }
+}
+
+// The CSS Color Module Level 4 named colors.
+const NAMES: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn parses_names_hex_and_rgb() {
+        let teal = Color::rgb(0, 128, 128);
+        for text in [
+            "teal",
+            "TEAL",
+            "#008080",
+            "rgb(0,128,128)",
+            " rgb(0 128 128) ",
+        ] {
+            assert_eq!(text.parse::<Color>(), Ok(teal), "{}", text);
+        }
+        assert_eq!("#0F8".parse(), Ok(Color::rgb(0x00, 0xff, 0x88)));
+        assert_eq!("rgb(100% 0% 50%)".parse(), Ok(Color::rgb(255, 0, 128)));
+        assert_eq!(Color::named("rebeccapurple"), Some(Color::hex(0x663399)));
+        assert_eq!(teal.to_string(), "#008080");
+
+        for text in [
+            "#12345",
+            "#ggg",
+            "rgb(256, 0, 0)",
+            "rgb(1, 2)",
+            "bluish",
+            "",
+        ] {
+            assert_eq!(
+                text.parse::<Color>(),
+                Err(ParseColorError(text.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn computes_wcag_contrast() {
+        assert_eq!(Color::BLACK.contrast_ratio(&Color::WHITE), 21.0);
+        assert_eq!(Color::WHITE.contrast_ratio(&Color::WHITE), 1.0);
+        let green = ContrastReport::new("PMWindow", Color::GREEN, Color::WHITE);
+        assert_eq!(format!("{:.2}", green.ratio), "5.14");
+        assert_eq!(green.level(), Some(WcagLevel::AA));
+        assert!(!green.passes(WcagLevel::AAA));
+        assert_eq!(
+            green.to_string(),
+            "PMWindow: #008000 on #ffffff has a contrast of 5.14:1 (AA)"
+        );
+        let pale = ContrastReport::new("Pale", Color::named("silver").unwrap(), Color::WHITE);
+        assert_eq!(pale.level(), None);
+    }
+
+    #[test]
+    fn registry_reports_failing_themes() {
+        let mut registry = ThemeRegistry::new();
+        assert_eq!(registry.contrast_failures(WcagLevel::AA), vec![]);
+        let failing: Vec<String> = registry
+            .contrast_failures(WcagLevel::AAA)
+            .into_iter()
+            .map(|report| report.theme)
+            .collect();
+        assert_eq!(failing, ["Motif", "PMWindow"]);
+
+        registry
+            .load(
+                "name = \"Fog\"\n[window]\nbackground = \"#eee\"\ntext = \"rgb(170, 170, 170)\"\n\
+                 [scroll_bar]\nwidth = 8\nheight = 100\n",
+            )
+            .unwrap();
+        let failing = registry.contrast_failures(WcagLevel::AA);
+        assert_eq!(failing.len(), 1);
+        assert_eq!(
+            failing[0].to_string(),
+            "Fog: #aaaaaa on #eeeeee has a contrast of 2.00:1 (fails AA)"
+        );
+    }
+}
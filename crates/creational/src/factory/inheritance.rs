@@ -0,0 +1,399 @@
+// Themes derived from another registered theme by overriding a few properties, usually
+// "the default, but with a different accent":
+//
+//   PMWindow  <--  Warm (window.text)  <--  Accent (button.background)
+//
+// Every property of a resolved theme remembers the theme it came from, `ResolvedTheme`
+// prints them like a theme file with the origin as a comment:
+//
+//   window.background = "#ffffff"   # PMWindow
+//   window.text = "#8b4513"         # Warm
+use std::fmt;
+use std::rc::Rc;
+
+use super::abstract_factory::WidgetFactory;
+use super::{Button, Color, Dialog, Label, Menu, ScrollBar, TextBox, ThemeInfo, Window};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Property {
+    WindowBackground,
+    WindowText,
+    WindowName,
+    ScrollBarWidth,
+    ScrollBarHeight,
+    ScrollBarName,
+    ButtonBackground,
+    ButtonText,
+}
+
+impl Property {
+    pub const ALL: [Property; 8] = [
+        Property::WindowBackground,
+        Property::WindowText,
+        Property::WindowName,
+        Property::ScrollBarWidth,
+        Property::ScrollBarHeight,
+        Property::ScrollBarName,
+        Property::ButtonBackground,
+        Property::ButtonText,
+    ];
+
+    // Same keys as the theme file format.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Property::WindowBackground => "window.background",
+            Property::WindowText => "window.text",
+            Property::WindowName => "window.name",
+            Property::ScrollBarWidth => "scroll_bar.width",
+            Property::ScrollBarHeight => "scroll_bar.height",
+            Property::ScrollBarName => "scroll_bar.name",
+            Property::ButtonBackground => "button.background",
+            Property::ButtonText => "button.text",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyValue {
+    Color(Color),
+    Size(i64),
+    Name(String),
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::Color(color) => write!(f, "\"{}\"", color),
+            PropertyValue::Size(size) => write!(f, "{}", size),
+            PropertyValue::Name(name) => write!(f, "{:?}", name),
+        }
+    }
+}
+
+// The properties a derived theme changes, the others come from the theme it extends.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThemeOverrides {
+    values: Vec<(Property, PropertyValue)>,
+}
+
+impl ThemeOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // Setting a property again replaces the previous value.
+    fn set(&mut self, property: Property, value: PropertyValue) -> &mut Self {
+        self.values.retain(|(existing, _)| *existing != property);
+        self.values.push((property, value));
+        self
+    }
+    pub fn window_background(&mut self, color: Color) -> &mut Self {
+        self.set(Property::WindowBackground, PropertyValue::Color(color))
+    }
+    pub fn window_text(&mut self, color: Color) -> &mut Self {
+        self.set(Property::WindowText, PropertyValue::Color(color))
+    }
+    pub fn window_name(&mut self, name: &str) -> &mut Self {
+        self.set(Property::WindowName, PropertyValue::Name(name.to_string()))
+    }
+    pub fn scroll_bar_size(&mut self, width: i64, height: i64) -> &mut Self {
+        self.set(Property::ScrollBarWidth, PropertyValue::Size(width))
+            .set(Property::ScrollBarHeight, PropertyValue::Size(height))
+    }
+    pub fn scroll_bar_name(&mut self, name: &str) -> &mut Self {
+        self.set(
+            Property::ScrollBarName,
+            PropertyValue::Name(name.to_string()),
+        )
+    }
+    pub fn button_background(&mut self, color: Color) -> &mut Self {
+        self.set(Property::ButtonBackground, PropertyValue::Color(color))
+    }
+    pub fn button_text(&mut self, color: Color) -> &mut Self {
+        self.set(Property::ButtonText, PropertyValue::Color(color))
+    }
+
+    pub(super) fn values(&self) -> &[(Property, PropertyValue)] {
+        &self.values
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedValue {
+    pub property: Property,
+    pub value: PropertyValue,
+    // the theme that set it last along the inheritance chain
+    pub source: String,
+}
+
+// A derived theme with every property looked up, see `ThemeRegistry::resolve`. The widgets
+// without properties of their own (menus, text boxes...) come from the base theme.
+pub struct ResolvedTheme {
+    pub name: String,
+    // the theme itself first, the base theme last
+    pub ancestry: Vec<String>,
+    // one per `Property::ALL`, in that order
+    pub values: Vec<ResolvedValue>,
+    base: Rc<dyn WidgetFactory>,
+}
+
+impl ResolvedTheme {
+    // `chain` is the derived themes from the most derived one up, `base` the registered
+    // factory at the root.
+    pub(super) fn new(
+        chain: &[(&str, &ThemeOverrides)],
+        base_name: &str,
+        base: Rc<dyn WidgetFactory>,
+    ) -> Self {
+        let (window, scroll_bar) = (base.create_window(), base.create_scroll_bar());
+        let button = base.create_button("");
+        let mut values: Vec<ResolvedValue> = Property::ALL
+            .into_iter()
+            .map(|property| {
+                let value = match property {
+                    Property::WindowBackground => PropertyValue::Color(window.background),
+                    Property::WindowText => PropertyValue::Color(window.text),
+                    Property::WindowName => PropertyValue::Name(window.widget_name.clone()),
+                    Property::ScrollBarWidth => PropertyValue::Size(scroll_bar.width),
+                    Property::ScrollBarHeight => PropertyValue::Size(scroll_bar.height),
+                    Property::ScrollBarName => PropertyValue::Name(scroll_bar.widget_name.clone()),
+                    Property::ButtonBackground => PropertyValue::Color(button.background),
+                    Property::ButtonText => PropertyValue::Color(button.text),
+                };
+                ResolvedValue {
+                    property,
+                    value,
+                    source: base_name.to_string(),
+                }
+            })
+            .collect();
+        // the closest theme wins, so apply from the base down
+        for (name, overrides) in chain.iter().rev() {
+            for (property, value) in overrides.values() {
+                let resolved = values
+                    .iter_mut()
+                    .find(|resolved| resolved.property == *property)
+                    .expect("every property has a value");
+                resolved.value = value.clone();
+                resolved.source = name.to_string();
+            }
+        }
+        let mut ancestry: Vec<String> = chain.iter().map(|(name, _)| name.to_string()).collect();
+        ancestry.push(base_name.to_string());
+        Self {
+            name: ancestry[0].clone(),
+            ancestry,
+            values,
+            base,
+        }
+    }
+
+    pub fn get(&self, property: Property) -> &ResolvedValue {
+        &self.values[Property::ALL
+            .iter()
+            .position(|known| *known == property)
+            .expect("every property is in Property::ALL")]
+    }
+    fn color(&self, property: Property) -> Color {
+        match self.get(property).value {
+            PropertyValue::Color(color) => color,
+            _ => unreachable!("{} is a color", property.key()),
+        }
+    }
+    fn size(&self, property: Property) -> i64 {
+        match self.get(property).value {
+            PropertyValue::Size(size) => size,
+            _ => unreachable!("{} is a size", property.key()),
+        }
+    }
+    fn name(&self, property: Property) -> String {
+        match &self.get(property).value {
+            PropertyValue::Name(name) => name.clone(),
+            _ => unreachable!("{} is a name", property.key()),
+        }
+    }
+}
+
+impl WidgetFactory for ResolvedTheme {
+    fn create_window(&self) -> Window {
+        Window {
+            background: self.color(Property::WindowBackground),
+            text: self.color(Property::WindowText),
+            widget_name: self.name(Property::WindowName),
+            theme: self.name.clone(),
+            widgets: vec![],
+        }
+    }
+    fn create_scroll_bar(&self) -> ScrollBar {
+        ScrollBar {
+            width: self.size(Property::ScrollBarWidth),
+            height: self.size(Property::ScrollBarHeight),
+            widget_name: self.name(Property::ScrollBarName),
+            theme: self.name.clone(),
+            position: 0,
+        }
+    }
+    fn get_theme_info(&self) -> ThemeInfo {
+        ThemeInfo {
+            name: self.name.clone(),
+        }
+    }
+
+    fn create_button(&self, label: &str) -> Button {
+        Button {
+            background: self.color(Property::ButtonBackground),
+            text: self.color(Property::ButtonText),
+            theme: self.name.clone(),
+            ..self.base.create_button(label)
+        }
+    }
+    fn create_menu(&self, items: &[&str]) -> Menu {
+        Menu {
+            theme: self.name.clone(),
+            ..self.base.create_menu(items)
+        }
+    }
+    fn create_text_box(&self) -> TextBox {
+        TextBox {
+            theme: self.name.clone(),
+            ..self.base.create_text_box()
+        }
+    }
+    fn create_label(&self, content: &str) -> Label {
+        Label {
+            theme: self.name.clone(),
+            ..self.base.create_label(content)
+        }
+    }
+    fn create_dialog(&self, title: &str, buttons: &[&str]) -> Dialog {
+        Dialog {
+            buttons: buttons
+                .iter()
+                .map(|label| self.create_button(label))
+                .collect(),
+            theme: self.name.clone(),
+            ..self.base.create_dialog(title, &[])
+        }
+    }
+}
+
+impl fmt::Display for ResolvedTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# {}", self.ancestry.join(" < "))?;
+        let lines: Vec<String> = self
+            .values
+            .iter()
+            .map(|resolved| format!("{} = {}", resolved.property.key(), resolved.value))
+            .collect();
+        let width = lines.iter().map(String::len).max().unwrap_or(0);
+        for (line, resolved) in lines.iter().zip(&self.values) {
+            writeln!(f, "{:width$}   # {}", line, resolved.source, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::abstract_factory::*;
+    use super::super::*;
+
+    fn registry() -> ThemeRegistry {
+        let mut registry = ThemeRegistry::new();
+        let mut warm = ThemeOverrides::new();
+        warm.window_text(Color::named("saddlebrown").unwrap())
+            .scroll_bar_size(12, 120);
+        let mut accent = ThemeOverrides::new();
+        accent
+            .button_background(Color::named("orange").unwrap())
+            .window_name("AccentWindow");
+        registry
+            .derive("Warm", "PMWindow", &warm)
+            .unwrap()
+            .derive("Accent", "Warm", &accent)
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn derived_themes_override_their_ancestors() {
+        let registry = registry();
+        assert_eq!(registry.themes(), ["Accent", "Motif", "PMWindow", "Warm"]);
+        let accent = registry.get("Accent").unwrap();
+        let window = accent.create_window();
+        assert_eq!(window.background, Color::WHITE);
+        assert_eq!(window.text, Color::hex(0x8b4513));
+        assert_eq!(window.widget_name, "AccentWindow");
+        assert_eq!(window.theme, "Accent");
+        assert_eq!(accent.create_scroll_bar().width, 12);
+        let button = accent.create_button("OK");
+        assert_eq!(button.background, Color::hex(0xffa500));
+        assert_eq!(button.widget_name, "PMButton");
+
+        let mut window = accent.create_window();
+        window
+            .add(accent.create_menu(&["File"]))
+            .add(accent.create_dialog("Quit?", &["Yes"]));
+        assert_eq!(window.check_theme(), Ok(()));
+        // the warm theme is unchanged by its child
+        assert_eq!(
+            registry.get("Warm").unwrap().create_window().widget_name,
+            "PMWindow"
+        );
+    }
+
+    #[test]
+    fn dumps_where_each_value_comes_from() {
+        let resolved = registry().resolve("Accent").unwrap();
+        assert_eq!(resolved.ancestry, ["Accent", "Warm", "PMWindow"]);
+        assert_eq!(resolved.get(Property::ScrollBarHeight).source, "Warm");
+        assert_eq!(
+            resolved.to_string(),
+            r##"# Accent < Warm < PMWindow
+window.background = "#ffffff"   # PMWindow
+window.text = "#8b4513"         # Warm
+window.name = "AccentWindow"    # Accent
+scroll_bar.width = 12           # Warm
+scroll_bar.height = 120         # Warm
+scroll_bar.name = "ScrollBar"   # PMWindow
+button.background = "#ffa500"   # Accent
+button.text = "#000000"         # PMWindow
+"##
+        );
+    }
+
+    #[test]
+    fn rejects_cycles_and_unknown_parents() {
+        let mut registry = registry();
+        let overrides = ThemeOverrides::new();
+        assert_eq!(
+            registry.derive("Dusk", "Aqua", &overrides).err(),
+            Some(ThemeError::UnknownTheme("Aqua".to_string()))
+        );
+        let cycle = registry.derive("Warm", "Accent", &overrides).err().unwrap();
+        assert_eq!(
+            cycle,
+            ThemeError::InheritanceCycle(vec![
+                "Warm".to_string(),
+                "Accent".to_string(),
+                "Warm".to_string()
+            ])
+        );
+        assert_eq!(
+            cycle.to_string(),
+            "theme inheritance cycle: Warm -> Accent -> Warm"
+        );
+        // the failed definition left the previous one in place
+        assert_eq!(
+            registry.resolve("Warm").unwrap().ancestry,
+            ["Warm", "PMWindow"]
+        );
+
+        // redefining a base theme is picked up by the themes derived from it
+        let mut motif = ThemeOverrides::new();
+        motif.window_background(Color::BLACK);
+        registry.derive("PMWindow", "Motif", &motif).unwrap();
+        let resolved = registry.resolve("Accent").unwrap();
+        assert_eq!(resolved.ancestry, ["Accent", "Warm", "PMWindow", "Motif"]);
+        assert_eq!(resolved.create_scroll_bar().widget_name, "MotifScollbar");
+    }
+}
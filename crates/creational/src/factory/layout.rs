@@ -0,0 +1,527 @@
+// Box layout for the widgets of a window. A layout is a tree of stacks whose leaves point at
+// `Window::widgets` by index, sizes are in character cells like `Canvas`:
+//
+//   column (padding 1 2)
+//   ├── menu              stretched across
+//   ├── row (spacing 1)
+//   │   ├── label
+//   │   └── text box      grows up to its max width
+//   └── button            aligned to the end
+//
+// A stack gives its children their preferred size along its direction, shares what is left
+// between the growing ones and takes it back from the last ones when short of room. Across,
+// each child is aligned on its own. `Layout::resize` computes the rectangles again, always
+// the same for the same tree, widgets and size.
+use std::fmt;
+
+use super::render::button_face;
+use super::{Widget, Window};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+}
+
+impl Rect {
+    pub fn new(x: i64, y: i64, width: i64, height: i64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+impl fmt::Display for Rect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{} at ({}, {})",
+            self.width, self.height, self.x, self.y
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Row,
+    Column,
+}
+
+// Placement across the parent's direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+    Stretch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    // the parent of a new node is a widget, or a node of another layout
+    NotAStack(NodeId),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::NotAStack(node) => {
+                write!(f, "node {} is not a stack of this layout", node.0)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+#[derive(Debug, Clone)]
+enum Content {
+    // index in `Window::widgets`
+    Widget(usize),
+    Stack {
+        direction: Direction,
+        spacing: i64,
+        children: Vec<NodeId>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    content: Content,
+    // (vertical, horizontal) on both sides
+    padding: (i64, i64),
+    align: Align,
+    grow: bool,
+    // (width, height), min wins over max
+    min: (i64, i64),
+    max: (i64, i64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Layout {
+    // the root stack first
+    nodes: Vec<Node>,
+    size: (i64, i64),
+    // one per node, empty until the first resize
+    rects: Vec<Rect>,
+}
+
+impl Layout {
+    pub fn new(direction: Direction) -> Self {
+        let mut layout = Self {
+            nodes: vec![],
+            size: (0, 0),
+            rects: vec![],
+        };
+        layout.push(Content::Stack {
+            direction,
+            spacing: 0,
+            children: vec![],
+        });
+        layout
+    }
+    // The window's widgets one below the other inside its border, what `Window::render`
+    // draws. Menus, scroll bars and dialogs span the whole width.
+    pub fn for_window(window: &Window) -> Self {
+        let mut layout = Self::new(Direction::Column);
+        let root = layout.root();
+        layout.padding(root, 1, 2);
+        for (index, widget) in window.widgets.iter().enumerate() {
+            let node = layout.widget(root, index).expect("the root is a stack");
+            if let Widget::Menu(_) | Widget::ScrollBar(_) | Widget::Dialog(_) = widget {
+                layout.align(node, Align::Stretch);
+            }
+        }
+        layout
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+    fn push(&mut self, content: Content) -> NodeId {
+        self.nodes.push(Node {
+            content,
+            padding: (0, 0),
+            align: Align::default(),
+            grow: false,
+            min: (0, 0),
+            max: (i64::MAX, i64::MAX),
+        });
+        NodeId(self.nodes.len() - 1)
+    }
+    fn add_child(&mut self, parent: NodeId, content: Content) -> Result<NodeId, LayoutError> {
+        if !matches!(
+            self.nodes.get(parent.0),
+            Some(Node {
+                content: Content::Stack { .. },
+                ..
+            })
+        ) {
+            return Err(LayoutError::NotAStack(parent));
+        }
+        let child = self.push(content);
+        if let Content::Stack { children, .. } = &mut self.nodes[parent.0].content {
+            children.push(child);
+        }
+        Ok(child)
+    }
+    pub fn stack(&mut self, parent: NodeId, direction: Direction) -> Result<NodeId, LayoutError> {
+        self.add_child(
+            parent,
+            Content::Stack {
+                direction,
+                spacing: 0,
+                children: vec![],
+            },
+        )
+    }
+    // `index` is the position of the widget in `Window::widgets`, missing widgets take no
+    // room.
+    pub fn widget(&mut self, parent: NodeId, index: usize) -> Result<NodeId, LayoutError> {
+        self.add_child(parent, Content::Widget(index))
+    }
+
+    // The setters ignore ids of other layouts, like `rect`.
+    pub fn padding(&mut self, node: NodeId, vertical: i64, horizontal: i64) -> &mut Self {
+        if let Some(node) = self.nodes.get_mut(node.0) {
+            node.padding = (vertical, horizontal);
+        }
+        self
+    }
+    // Space between the children of a stack.
+    pub fn spacing(&mut self, node: NodeId, spacing: i64) -> &mut Self {
+        if let Some(Node {
+            content: Content::Stack {
+                spacing: current, ..
+            },
+            ..
+        }) = self.nodes.get_mut(node.0)
+        {
+            *current = spacing;
+        }
+        self
+    }
+    pub fn align(&mut self, node: NodeId, align: Align) -> &mut Self {
+        if let Some(node) = self.nodes.get_mut(node.0) {
+            node.align = align;
+        }
+        self
+    }
+    // Takes a share of the room left in its parent's direction.
+    pub fn grow(&mut self, node: NodeId) -> &mut Self {
+        if let Some(node) = self.nodes.get_mut(node.0) {
+            node.grow = true;
+        }
+        self
+    }
+    pub fn min_size(&mut self, node: NodeId, width: i64, height: i64) -> &mut Self {
+        if let Some(node) = self.nodes.get_mut(node.0) {
+            node.min = (width, height);
+        }
+        self
+    }
+    pub fn max_size(&mut self, node: NodeId, width: i64, height: i64) -> &mut Self {
+        if let Some(node) = self.nodes.get_mut(node.0) {
+            node.max = (width, height);
+        }
+        self
+    }
+
+    // The size the whole tree needs to show every widget unclipped.
+    pub fn preferred_size(&self, window: &Window) -> (i64, i64) {
+        self.preferred(self.root(), window)
+    }
+    pub fn size(&self) -> (i64, i64) {
+        self.size
+    }
+    // Lays the tree out again for a window of `width` x `height`.
+    pub fn resize(&mut self, window: &Window, width: i64, height: i64) -> &mut Self {
+        self.size = (width, height);
+        let mut rects = vec![Rect::default(); self.nodes.len()];
+        self.place(
+            self.root(),
+            Rect::new(0, 0, width, height),
+            window,
+            &mut rects,
+        );
+        self.rects = rects;
+        self
+    }
+    // Absolute position of a node after the last resize.
+    pub fn rect(&self, node: NodeId) -> Rect {
+        self.rects.get(node.0).copied().unwrap_or_default()
+    }
+    pub fn widget_rect(&self, index: usize) -> Option<Rect> {
+        self.nodes
+            .iter()
+            .position(|node| matches!(node.content, Content::Widget(i) if i == index))
+            .map(|node| self.rect(NodeId(node)))
+    }
+
+    fn preferred(&self, id: NodeId, window: &Window) -> (i64, i64) {
+        let node = &self.nodes[id.0];
+        let (width, height) = match &node.content {
+            Content::Widget(index) => window.widgets.get(*index).map_or((0, 0), intrinsic_size),
+            Content::Stack {
+                direction,
+                spacing,
+                children,
+            } => {
+                let sizes: Vec<(i64, i64)> = children
+                    .iter()
+                    .map(|child| along(*direction, self.preferred(*child, window)))
+                    .collect();
+                let gaps = spacing * (children.len() as i64 - 1).max(0);
+                let main = sizes.iter().map(|size| size.0).sum::<i64>() + gaps;
+                let cross = sizes.iter().map(|size| size.1).max().unwrap_or(0);
+                let (width, height) = along(*direction, (main, cross));
+                (width + 2 * node.padding.1, height + 2 * node.padding.0)
+            }
+        };
+        (
+            clamp(width, node.min.0, node.max.0),
+            clamp(height, node.min.1, node.max.1),
+        )
+    }
+
+    fn place(&self, id: NodeId, rect: Rect, window: &Window, rects: &mut [Rect]) {
+        rects[id.0] = rect;
+        let node = &self.nodes[id.0];
+        let Content::Stack {
+            direction,
+            spacing,
+            children,
+        } = &node.content
+        else {
+            return;
+        };
+        let direction = *direction;
+        // (main, cross) of the content box
+        let (x, y) = (rect.x + node.padding.1, rect.y + node.padding.0);
+        let (width, height) = (
+            (rect.width - 2 * node.padding.1).max(0),
+            (rect.height - 2 * node.padding.0).max(0),
+        );
+        let (start, cross_start) = along(direction, (x, y));
+        let (room, cross_room) = along(direction, (width, height));
+
+        let bounds: Vec<((i64, i64), (i64, i64))> = children
+            .iter()
+            .map(|child| {
+                let child = &self.nodes[child.0];
+                (along(direction, child.min), along(direction, child.max))
+            })
+            .collect();
+        let preferred: Vec<(i64, i64)> = children
+            .iter()
+            .map(|child| along(direction, self.preferred(*child, window)))
+            .collect();
+        let mut sizes: Vec<i64> = preferred.iter().map(|size| size.0).collect();
+        let gaps = spacing * (children.len() as i64 - 1).max(0);
+        let mut left = room - gaps - sizes.iter().sum::<i64>();
+        // share the room left equally, the first children get the cells that do not divide
+        while left > 0 {
+            let growing: Vec<usize> = (0..children.len())
+                .filter(|&i| self.nodes[children[i].0].grow && sizes[i] < bounds[i].1 .0)
+                .collect();
+            if growing.is_empty() {
+                break;
+            }
+            let share = (left / growing.len() as i64).max(1);
+            for i in growing {
+                let given = share.min(bounds[i].1 .0 - sizes[i]).min(left);
+                sizes[i] += given;
+                left -= given;
+            }
+        }
+        // short of room: shrink from the last child, never below its min
+        for i in (0..children.len()).rev() {
+            if left >= 0 {
+                break;
+            }
+            let taken = (-left).min(sizes[i] - bounds[i].0 .0).max(0);
+            sizes[i] -= taken;
+            left += taken;
+        }
+
+        let mut main = start;
+        for (i, child) in children.iter().enumerate() {
+            let ((_, min), (_, max)) = bounds[i];
+            let align = self.nodes[child.0].align;
+            let cross = match align {
+                Align::Stretch => clamp(cross_room, min, max),
+                _ => preferred[i].1.min(cross_room).max(min),
+            };
+            let offset = match align {
+                Align::Start | Align::Stretch => 0,
+                Align::Center => (cross_room - cross).max(0) / 2,
+                Align::End => (cross_room - cross).max(0),
+            };
+            let (x, y) = along(direction, (main, cross_start + offset));
+            let (width, height) = along(direction, (sizes[i], cross));
+            self.place(*child, Rect::new(x, y, width, height), window, rects);
+            main += sizes[i] + spacing;
+        }
+    }
+}
+
+// Swaps (width, height) into (main, cross) for a direction and back.
+fn along(direction: Direction, (a, b): (i64, i64)) -> (i64, i64) {
+    match direction {
+        Direction::Row => (a, b),
+        Direction::Column => (b, a),
+    }
+}
+
+fn clamp(value: i64, min: i64, max: i64) -> i64 {
+    value.min(max).max(min)
+}
+
+// What a widget needs to be drawn in full, see `Window::render`.
+fn intrinsic_size(widget: &Widget) -> (i64, i64) {
+    let chars = |text: &str| text.chars().count() as i64;
+    match widget {
+        // both arrows and one cell of track
+        Widget::ScrollBar(_) => (3, 1),
+        Widget::Button(button) => (chars(&button_face(button)), 1),
+        Widget::Menu(menu) => (menu.items.iter().map(|item| chars(item) + 2).sum(), 1),
+        Widget::TextBox(text_box) => {
+            let border = if text_box.border_width > 0 { 2 } else { 0 };
+            (text_box.width.max(0) + border, 1)
+        }
+        Widget::Label(label) => (chars(&label.content), 1),
+        Widget::Dialog(dialog) => {
+            let buttons: i64 = dialog
+                .buttons
+                .iter()
+                .map(|button| chars(&button_face(button)) + 1)
+                .sum();
+            ((chars(&dialog.title) + 6).max(buttons + 3), 3)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::abstract_factory::*;
+    use super::super::*;
+
+    fn form() -> Window {
+        let pm = PMWindowWidget {};
+        let mut window = pm.create_window();
+        window
+            .add(pm.create_menu(&["File", "Edit"]))
+            .add(pm.create_label("Name"))
+            .add(pm.create_text_box())
+            .add(pm.create_button("OK"));
+        window
+    }
+
+    #[test]
+    fn stacks_widgets_with_padding_and_alignment() {
+        let window = form();
+        let mut layout = Layout::new(Direction::Column);
+        let root = layout.root();
+        layout.padding(root, 1, 2).spacing(root, 1);
+        let menu = layout.widget(root, 0).unwrap();
+        let row = layout.stack(root, Direction::Row).unwrap();
+        layout.widget(row, 1).unwrap();
+        let text_box = layout.widget(row, 2).unwrap();
+        let button = layout.widget(root, 3).unwrap();
+        layout
+            .align(menu, Align::Stretch)
+            .align(row, Align::Stretch)
+            .spacing(row, 1)
+            .grow(text_box)
+            .max_size(text_box, 24, 1)
+            .align(button, Align::End);
+
+        // menu, row and button with a blank line between them
+        assert_eq!(layout.preferred_size(&window), (4 + 5 + 22, 2 + 5));
+        layout.resize(&window, 40, 10);
+        assert_eq!(layout.size(), (40, 10));
+        assert_eq!(layout.rect(root), Rect::new(0, 0, 40, 10));
+        assert_eq!(layout.rect(menu), Rect::new(2, 1, 36, 1));
+        assert_eq!(layout.rect(row), Rect::new(2, 3, 36, 1));
+        assert_eq!(layout.widget_rect(1), Some(Rect::new(2, 3, 4, 1)));
+        // grew from 22 to its max
+        assert_eq!(layout.rect(text_box), Rect::new(7, 3, 24, 1));
+        assert_eq!(layout.rect(button), Rect::new(32, 5, 6, 1));
+        assert_eq!(layout.widget_rect(4), None);
+    }
+
+    #[test]
+    fn rejects_parents_that_are_not_stacks_of_the_layout() {
+        let window = form();
+        let mut layout = Layout::new(Direction::Column);
+        let root = layout.root();
+        let label = layout.widget(root, 1).unwrap();
+        assert_eq!(layout.widget(label, 2), Err(LayoutError::NotAStack(label)));
+        let mut other = Layout::new(Direction::Row);
+        let other_root = other.root();
+        let foreign = other.stack(other_root, Direction::Column).unwrap();
+        let foreign = other.stack(foreign, Direction::Column).unwrap();
+        assert_eq!(
+            layout
+                .stack(foreign, Direction::Row)
+                .unwrap_err()
+                .to_string(),
+            "node 2 is not a stack of this layout"
+        );
+
+        // unknown ids change nothing
+        layout
+            .padding(foreign, 1, 1)
+            .spacing(foreign, 1)
+            .align(foreign, Align::End)
+            .grow(foreign)
+            .min_size(foreign, 5, 5)
+            .max_size(foreign, 5, 5)
+            .resize(&window, 10, 3);
+        assert_eq!(layout.rect(label), Rect::new(0, 0, 4, 1));
+        assert_eq!(layout.rect(foreign), Rect::default());
+    }
+
+    #[test]
+    fn recomputes_the_same_rectangles_on_resize() {
+        let window = form();
+        let mut layout = Layout::new(Direction::Row);
+        let root = layout.root();
+        let (label, text_box, button) = (
+            layout.widget(root, 1).unwrap(),
+            layout.widget(root, 2).unwrap(),
+            layout.widget(root, 3).unwrap(),
+        );
+        layout
+            .grow(label)
+            .grow(text_box)
+            .align(label, Align::Center)
+            .min_size(text_box, 10, 1);
+
+        // 7 cells left, the label gets the odd one
+        layout.resize(&window, 39, 3);
+        let wide = layout.clone();
+        assert_eq!(layout.rect(label), Rect::new(0, 1, 8, 1));
+        assert_eq!(layout.rect(text_box), Rect::new(8, 0, 25, 1));
+        assert_eq!(layout.rect(button), Rect::new(33, 0, 6, 1));
+
+        // short of room the button goes first, then the text box down to its min
+        layout.resize(&window, 12, 3);
+        assert_eq!(layout.rect(label), Rect::new(0, 1, 2, 1));
+        assert_eq!(layout.rect(text_box), Rect::new(2, 0, 10, 1));
+        assert_eq!(layout.rect(button), Rect::new(12, 0, 0, 1));
+
+        layout.resize(&window, 39, 3);
+        assert_eq!(layout.rects, wide.rects);
+        assert_eq!(layout.rect(text_box).to_string(), "25x1 at (8, 0)");
+    }
+}
@@ -0,0 +1,324 @@
+// Switching themes while the application runs. The manager hands out shared widgets and
+// keeps a weak reference to each, a switch restyles the ones still alive in place:
+//
+//   let window = manager.create_window();          // Rc<RefCell<Window>>
+//   manager.subscribe(|change| println!("{} -> {}", change.from, change.to));
+//   manager.switch("Motif")?;                       // same `window`, Motif colors
+//
+// Only the look comes from the new factory, what the user did with a widget (labels, text
+// typed, scroll position, child widgets) stays.
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use super::abstract_factory::WidgetFactory;
+use super::{
+    Button, Dialog, Label, Menu, ScrollBar, TextBox, ThemeError, ThemeRegistry, Widget, Window,
+};
+
+// Takes the look of `factory`'s widgets and keeps its own state.
+pub trait Restyle {
+    fn restyle(&mut self, factory: &dyn WidgetFactory);
+}
+
+impl Restyle for Window {
+    fn restyle(&mut self, factory: &dyn WidgetFactory) {
+        let widgets = std::mem::take(&mut self.widgets);
+        *self = Window {
+            widgets,
+            ..factory.create_window()
+        };
+        for widget in &mut self.widgets {
+            widget.restyle(factory);
+        }
+    }
+}
+
+impl Restyle for ScrollBar {
+    fn restyle(&mut self, factory: &dyn WidgetFactory) {
+        *self = ScrollBar {
+            position: self.position,
+            ..factory.create_scroll_bar()
+        };
+    }
+}
+
+impl Restyle for Button {
+    fn restyle(&mut self, factory: &dyn WidgetFactory) {
+        *self = factory.create_button(&self.label);
+    }
+}
+
+impl Restyle for Menu {
+    fn restyle(&mut self, factory: &dyn WidgetFactory) {
+        let items: Vec<&str> = self.items.iter().map(String::as_str).collect();
+        *self = factory.create_menu(&items);
+    }
+}
+
+impl Restyle for TextBox {
+    fn restyle(&mut self, factory: &dyn WidgetFactory) {
+        *self = TextBox {
+            value: std::mem::take(&mut self.value),
+            ..factory.create_text_box()
+        };
+    }
+}
+
+impl Restyle for Label {
+    fn restyle(&mut self, factory: &dyn WidgetFactory) {
+        *self = factory.create_label(&self.content);
+    }
+}
+
+impl Restyle for Dialog {
+    fn restyle(&mut self, factory: &dyn WidgetFactory) {
+        let buttons = std::mem::take(&mut self.buttons);
+        *self = Dialog {
+            buttons,
+            ..factory.create_dialog(&self.title, &[])
+        };
+        for button in &mut self.buttons {
+            button.restyle(factory);
+        }
+    }
+}
+
+impl Restyle for Widget {
+    fn restyle(&mut self, factory: &dyn WidgetFactory) {
+        match self {
+            Widget::ScrollBar(widget) => widget.restyle(factory),
+            Widget::Button(widget) => widget.restyle(factory),
+            Widget::Menu(widget) => widget.restyle(factory),
+            Widget::TextBox(widget) => widget.restyle(factory),
+            Widget::Label(widget) => widget.restyle(factory),
+            Widget::Dialog(widget) => widget.restyle(factory),
+        }
+    }
+}
+
+// Sent to subscribers once every live widget has been restyled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeChange {
+    pub from: String,
+    pub to: String,
+    pub restyled: usize,
+    // widgets borrowed elsewhere during the switch, they keep their look until the next one
+    pub busy: usize,
+}
+
+type Subscriber = Box<dyn FnMut(&ThemeChange)>;
+
+pub struct ThemeManager {
+    registry: ThemeRegistry,
+    theme: String,
+    factory: Rc<dyn WidgetFactory>,
+    // dropped widgets are forgotten on the next switch
+    widgets: Vec<Weak<RefCell<dyn Restyle>>>,
+    subscribers: Vec<Subscriber>,
+}
+
+impl Default for ThemeManager {
+    fn default() -> Self {
+        Self::new(ThemeRegistry::default())
+    }
+}
+
+impl ThemeManager {
+    // Starts with the registry's default theme.
+    pub fn new(registry: ThemeRegistry) -> Self {
+        let theme = registry.default_theme().to_string();
+        Self {
+            factory: registry.get_or_default(&theme),
+            registry,
+            theme,
+            widgets: vec![],
+            subscribers: vec![],
+        }
+    }
+    pub fn registry(&self) -> &ThemeRegistry {
+        &self.registry
+    }
+    // Changes to the current theme show up on the next `switch`.
+    pub fn registry_mut(&mut self) -> &mut ThemeRegistry {
+        &mut self.registry
+    }
+    pub fn theme(&self) -> &str {
+        &self.theme
+    }
+    pub fn factory(&self) -> Rc<dyn WidgetFactory> {
+        Rc::clone(&self.factory)
+    }
+
+    // Restyles `widget` on every switch for as long as it is alive elsewhere, tracking it
+    // again changes nothing.
+    pub fn track<T: Restyle + 'static>(&mut self, widget: &Rc<RefCell<T>>) -> &mut Self {
+        let widget: Rc<RefCell<dyn Restyle>> = Rc::clone(widget) as _;
+        let widget = Rc::downgrade(&widget);
+        if !self
+            .widgets
+            .iter()
+            .any(|tracked| Weak::ptr_eq(tracked, &widget))
+        {
+            self.widgets.push(widget);
+        }
+        self
+    }
+    // A window of the current theme, tracked. Widgets added to it are restyled with it.
+    pub fn create_window(&mut self) -> Rc<RefCell<Window>> {
+        let window = Rc::new(RefCell::new(self.factory.create_window()));
+        self.track(&window);
+        window
+    }
+    pub fn create_scroll_bar(&mut self) -> Rc<RefCell<ScrollBar>> {
+        let scroll_bar = Rc::new(RefCell::new(self.factory.create_scroll_bar()));
+        self.track(&scroll_bar);
+        scroll_bar
+    }
+    // Tracked widgets that are still alive.
+    pub fn tracked(&self) -> usize {
+        self.widgets
+            .iter()
+            .filter(|widget| widget.strong_count() > 0)
+            .count()
+    }
+
+    pub fn subscribe(&mut self, subscriber: impl FnMut(&ThemeChange) + 'static) -> &mut Self {
+        self.subscribers.push(Box::new(subscriber));
+        self
+    }
+
+    // Switching to the current theme applies it again, e.g. after it was redefined in the
+    // registry. An unknown name changes nothing.
+    pub fn switch(&mut self, name: &str) -> Result<ThemeChange, ThemeError> {
+        let factory = self
+            .registry
+            .get(name)
+            .ok_or_else(|| ThemeError::UnknownTheme(name.to_string()))?;
+        self.widgets.retain(|widget| widget.strong_count() > 0);
+        let (mut restyled, mut busy) = (0, 0);
+        for widget in self.widgets.iter().filter_map(Weak::upgrade) {
+            // e.g. a window borrowed by the handler that asked for the switch
+            match widget.try_borrow_mut() {
+                Ok(mut widget) => {
+                    widget.restyle(factory.as_ref());
+                    restyled += 1;
+                }
+                Err(_) => busy += 1,
+            }
+        }
+        let change = ThemeChange {
+            from: std::mem::replace(&mut self.theme, name.to_string()),
+            to: name.to_string(),
+            restyled,
+            busy,
+        };
+        self.factory = factory;
+        for subscriber in &mut self.subscribers {
+            subscriber(&change);
+        }
+        Ok(change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::*;
+
+    #[test]
+    fn restyles_live_widgets_in_place() {
+        let mut manager = ThemeManager::default();
+        assert_eq!(manager.theme(), "PMWindow");
+        let window = manager.create_window();
+        let factory = manager.factory();
+        let mut scroll_bar = factory.create_scroll_bar();
+        scroll_bar.position = 42;
+        let mut text_box = factory.create_text_box();
+        text_box.value = "ada".to_string();
+        window
+            .borrow_mut()
+            .add(scroll_bar)
+            .add(text_box)
+            .add(factory.create_dialog("Quit?", &["Yes", "No"]));
+        let alias = Rc::clone(&window);
+
+        let change = manager.switch("Motif").unwrap();
+        assert_eq!(
+            change,
+            ThemeChange {
+                from: "PMWindow".to_string(),
+                to: "Motif".to_string(),
+                restyled: 1,
+                busy: 0,
+            }
+        );
+        assert!(Rc::ptr_eq(&alias, &window));
+        let window = window.borrow();
+        assert_eq!(window.theme, "Motif Widget");
+        assert_eq!(window.check_theme(), Ok(()));
+        match &window.widgets[..] {
+            [Widget::ScrollBar(scroll_bar), Widget::TextBox(text_box), Widget::Dialog(dialog)] => {
+                assert_eq!(scroll_bar.widget_name, "MotifScollbar");
+                assert_eq!(scroll_bar.position, 42);
+                assert_eq!((text_box.value.as_str(), text_box.width), ("ada", 24));
+                assert_eq!(dialog.title, "Quit?");
+                assert_eq!(dialog.buttons[1].label, "No");
+                assert_eq!(dialog.buttons[1].border_width, 2);
+            }
+            widgets => panic!("unexpected widgets {:?}", widgets),
+        }
+    }
+
+    #[test]
+    fn notifies_subscribers_and_forgets_dropped_widgets() {
+        let mut manager = ThemeManager::default();
+        let changes = Rc::new(RefCell::new(vec![]));
+        let seen = Rc::clone(&changes);
+        manager.subscribe(move |change| seen.borrow_mut().push(change.to.clone()));
+        let window = manager.create_window();
+        let scroll_bar = manager.create_scroll_bar();
+        let label = Rc::new(RefCell::new(manager.factory().create_label("Name")));
+        manager.track(&label).track(&label).track(&window);
+        assert_eq!(manager.tracked(), 3);
+
+        drop(scroll_bar);
+        assert_eq!(manager.switch("Motif").unwrap().restyled, 2);
+        assert_eq!(manager.tracked(), 2);
+        assert_eq!(label.borrow().widget_name, "MotifLabel");
+
+        assert_eq!(
+            manager.switch("Aqua").err(),
+            Some(ThemeError::UnknownTheme("Aqua".to_string()))
+        );
+        assert_eq!(manager.theme(), "Motif");
+
+        let mut dark = ThemeOverrides::new();
+        dark.window_background(Color::BLACK);
+        manager
+            .registry_mut()
+            .derive("Dark", "PMWindow", &dark)
+            .unwrap();
+        manager.switch("Dark").unwrap();
+        assert_eq!(window.borrow().background, Color::BLACK);
+        assert_eq!(window.borrow().theme, "Dark");
+        assert_eq!(*changes.borrow(), ["Motif", "Dark"]);
+    }
+
+    #[test]
+    fn skips_widgets_borrowed_during_the_switch() {
+        let mut manager = ThemeManager::default();
+        let window = manager.create_window();
+        let label = Rc::new(RefCell::new(manager.factory().create_label("Name")));
+        manager.track(&label);
+
+        let reading = window.borrow();
+        let change = manager.switch("Motif").unwrap();
+        assert_eq!((change.restyled, change.busy), (1, 1));
+        assert_eq!(reading.theme, "PMWindow Widget");
+        drop(reading);
+
+        let change = manager.switch("Motif").unwrap();
+        assert_eq!((change.restyled, change.busy), (2, 0));
+        assert_eq!(window.borrow().theme, "Motif Widget");
+    }
+}
@@ -0,0 +1,248 @@
+// Themes looked up by name at runtime instead of a closed enum, so an application adds its
+// own look by registering a `WidgetFactory`:
+//
+//   registry.register("Solarized", Rc::new(SolarizedWidget {}));
+//   let factory = registry.get_or_default(&settings.theme);
+//
+// The built-in PMWindow and Motif themes are always registered, PMWindow is the default.
+// A theme can also be derived from another one by name, see `ThemeRegistry::derive`.
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use super::abstract_factory::{MotifWidget, PMWindowWidget, WidgetFactory};
+use super::{ContrastReport, ResolvedTheme, ThemeFile, ThemeFileError, ThemeOverrides, WcagLevel};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThemeError {
+    // no theme is registered under this name
+    UnknownTheme(String),
+    // the derived themes from the one being resolved back to itself
+    InheritanceCycle(Vec<String>),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::UnknownTheme(name) => write!(f, "no theme named `{}`", name),
+            ThemeError::InheritanceCycle(names) => {
+                write!(f, "theme inheritance cycle: {}", names.join(" -> "))
+            }
+        }
+    }
+}
+
+impl Error for ThemeError {}
+
+pub struct ThemeRegistry {
+    factories: BTreeMap<String, Rc<dyn WidgetFactory>>,
+    // name -> (parent name, overrides), resolved on every lookup so a derived theme follows
+    // changes of its ancestors
+    derived: BTreeMap<String, (String, ThemeOverrides)>,
+    // always a registered name, themes cannot be removed
+    default: String,
+}
+
+impl Default for ThemeRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            factories: BTreeMap::new(),
+            derived: BTreeMap::new(),
+            default: "PMWindow".to_string(),
+        };
+        registry
+            .register("PMWindow", Rc::new(PMWindowWidget {}))
+            .register("Motif", Rc::new(MotifWidget {}));
+        registry
+    }
+}
+
+impl ThemeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // Registering a name again replaces the previous factory.
+    pub fn register(&mut self, name: &str, factory: Rc<dyn WidgetFactory>) -> &mut Self {
+        self.derived.remove(name);
+        self.factories.insert(name.to_string(), factory);
+        self
+    }
+    // Registers `name` as `extends` with some properties overridden. Fails when `extends`
+    // is unknown or is itself derived from `name`, the registry is then left unchanged.
+    pub fn derive(
+        &mut self,
+        name: &str,
+        extends: &str,
+        overrides: &ThemeOverrides,
+    ) -> Result<&mut Self, ThemeError> {
+        let definition = (extends.to_string(), overrides.clone());
+        let previous = self.derived.insert(name.to_string(), definition);
+        if let Err(error) = self.ancestry(name) {
+            match previous {
+                Some(previous) => self.derived.insert(name.to_string(), previous),
+                None => self.derived.remove(name),
+            };
+            return Err(error);
+        }
+        self.factories.remove(name);
+        Ok(self)
+    }
+    // Parses a theme file and registers it under the name it declares.
+    pub fn load(&mut self, source: &str) -> Result<&mut Self, ThemeFileError> {
+        let theme = ThemeFile::parse(source)?;
+        Ok(self.register(&theme.name.clone(), Rc::new(theme)))
+    }
+    pub fn set_default(&mut self, name: &str) -> Result<&mut Self, ThemeError> {
+        if !self.is_registered(name) {
+            return Err(ThemeError::UnknownTheme(name.to_string()));
+        }
+        self.default = name.to_string();
+        Ok(self)
+    }
+
+    // Registered and derived names in alphabetical order.
+    pub fn themes(&self) -> Vec<&str> {
+        let names: BTreeSet<&str> = self
+            .factories
+            .keys()
+            .chain(self.derived.keys())
+            .map(String::as_str)
+            .collect();
+        names.into_iter().collect()
+    }
+    pub fn default_theme(&self) -> &str {
+        &self.default
+    }
+    fn is_registered(&self, name: &str) -> bool {
+        self.factories.contains_key(name) || self.derived.contains_key(name)
+    }
+    pub fn get(&self, name: &str) -> Option<Rc<dyn WidgetFactory>> {
+        match self.factories.get(name) {
+            Some(factory) => Some(Rc::clone(factory)),
+            None => self
+                .resolve(name)
+                .ok()
+                .map(|theme| Rc::new(theme) as Rc<dyn WidgetFactory>),
+        }
+    }
+    // A theme name from user settings may be stale, the default keeps the UI usable.
+    pub fn get_or_default(&self, name: &str) -> Rc<dyn WidgetFactory> {
+        self.get(name)
+            .or_else(|| self.get(&self.default))
+            .expect("the default theme is registered")
+    }
+
+    // The derived themes from `name` up to the registered theme at the root.
+    fn ancestry(&self, name: &str) -> Result<(Vec<&str>, &str), ThemeError> {
+        let mut chain: Vec<&str> = vec![];
+        let mut current = name;
+        while let Some((name, (extends, _))) = self.derived.get_key_value(current) {
+            chain.push(name);
+            if chain.contains(&extends.as_str()) {
+                let mut cycle: Vec<String> = chain.iter().map(|name| name.to_string()).collect();
+                cycle.push(extends.clone());
+                return Err(ThemeError::InheritanceCycle(cycle));
+            }
+            current = extends;
+        }
+        match self.factories.get_key_value(current) {
+            Some((base, _)) => Ok((chain, base)),
+            None => Err(ThemeError::UnknownTheme(current.to_string())),
+        }
+    }
+    // Every property of a theme with the ancestor it comes from, registered themes resolve
+    // to their own values.
+    pub fn resolve(&self, name: &str) -> Result<ResolvedTheme, ThemeError> {
+        let (chain, base) = self.ancestry(name)?;
+        let chain: Vec<(&str, &ThemeOverrides)> = chain
+            .into_iter()
+            .map(|name| (name, &self.derived[name].1))
+            .collect();
+        Ok(ResolvedTheme::new(
+            &chain,
+            base,
+            Rc::clone(&self.factories[base]),
+        ))
+    }
+
+    // Window text contrast of every registered theme below `level`, in name order.
+    pub fn contrast_failures(&self, level: WcagLevel) -> Vec<ContrastReport> {
+        self.themes()
+            .into_iter()
+            .filter_map(|name| self.get(name).map(|factory| (name, factory)))
+            .map(|(name, factory)| {
+                let window = factory.create_window();
+                ContrastReport::new(name, window.text, window.background)
+            })
+            .filter(|report| !report.passes(level))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::*;
+
+    struct HighContrast {}
+    impl WidgetFactory for HighContrast {
+        fn create_window(&self) -> Window {
+            Window {
+                background: Color::BLACK,
+                text: Color::named("yellow").unwrap(),
+                widget_name: "HighContrastWindow".to_string(),
+                ..Window::default()
+            }
+        }
+        fn create_scroll_bar(&self) -> ScrollBar {
+            ScrollBar {
+                width: 400,
+                height: 400,
+                widget_name: "HighContrastScrollBar".to_string(),
+                ..ScrollBar::default()
+            }
+        }
+        fn get_theme_info(&self) -> ThemeInfo {
+            ThemeInfo {
+                name: "High Contrast".into(),
+            }
+        }
+    }
+
+    #[test]
+    fn registers_and_looks_up_themes_by_name() {
+        let mut registry = ThemeRegistry::new();
+        assert_eq!(registry.themes(), ["Motif", "PMWindow"]);
+        registry.register("HighContrast", Rc::new(HighContrast {}));
+        assert_eq!(registry.themes(), ["HighContrast", "Motif", "PMWindow"]);
+
+        let factory = registry.get("HighContrast").unwrap();
+        assert_eq!(factory.create_window().background, Color::BLACK);
+        assert_eq!(
+            registry.get("Motif").unwrap().get_theme_info().name,
+            "Motif Widget"
+        );
+        assert!(registry.get("motif").is_none());
+    }
+
+    #[test]
+    fn unknown_names_fall_back_to_the_default() {
+        let mut registry = ThemeRegistry::new();
+        assert_eq!(registry.default_theme(), "PMWindow");
+        let fallback = registry.get_or_default("Aqua");
+        assert_eq!(fallback.get_theme_info().name, "PMWindow Widget");
+
+        registry.register("HighContrast", Rc::new(HighContrast {}));
+        registry.set_default("HighContrast").unwrap();
+        assert_eq!(
+            registry.get_or_default("Aqua").create_scroll_bar().width,
+            400
+        );
+        assert_eq!(
+            registry.set_default("Aqua").err(),
+            Some(ThemeError::UnknownTheme("Aqua".to_string()))
+        );
+        assert_eq!(registry.default_theme(), "HighContrast");
+    }
+}
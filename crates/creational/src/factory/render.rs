@@ -0,0 +1,434 @@
+// Draws a window and its widgets on a character grid, one widget per row (dialogs take
+// three) or where a `Layout` puts them, so a theme can be previewed in a terminal and its
+// output compared as text:
+//
+//   ┌─ PMWindow ─────────────┐
+//   │  File  Edit            │
+//   │ Name                   │
+//   │ [ OK ]                 │
+//   │ ◄█████░░░░░░░░░░░░░░░► │
+//   └────────────────────────┘
+//
+// `Canvas` prints as plain text, `Canvas::to_ansi` adds 24-bit colors from the theme.
+use std::fmt;
+
+use super::{Button, Color, Dialog, Layout, Menu, Rect, ScrollBar, TextBox, Widget, Window};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    // row after row
+    cells: Vec<Cell>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize, fg: Color, bg: Color) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell { ch: ' ', fg, bg }; width * height],
+        }
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn cell(&self, x: usize, y: usize) -> Option<&Cell> {
+        if x < self.width && y < self.height {
+            self.cells.get(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    // Anything outside the grid is clipped.
+    fn put(&mut self, x: usize, y: usize, ch: char, fg: Color, bg: Color) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = Cell { ch, fg, bg };
+        }
+    }
+    // Writes at most `max` characters, returns how many were written.
+    fn text(&mut self, x: usize, y: usize, text: &str, max: usize, fg: Color, bg: Color) -> usize {
+        let mut written = 0;
+        for ch in text.chars().take(max) {
+            self.put(x + written, y, ch, fg, bg);
+            written += 1;
+        }
+        written
+    }
+    fn fill(&mut self, x: usize, y: usize, width: usize, fg: Color, bg: Color) {
+        for column in x..x + width {
+            self.put(column, y, ' ', fg, bg);
+        }
+    }
+    // A box with `title` in its top border, `height` includes both borders.
+    fn frame(
+        &mut self,
+        x: usize,
+        y: usize,
+        (width, height): (usize, usize),
+        title: &str,
+        fg: Color,
+        bg: Color,
+    ) {
+        if width < 2 || height < 2 {
+            return;
+        }
+        let (right, bottom) = (x + width - 1, y + height - 1);
+        for row in y..=bottom {
+            self.fill(x, row, width, fg, bg);
+            self.put(x, row, '│', fg, bg);
+            self.put(right, row, '│', fg, bg);
+        }
+        for column in x + 1..right {
+            self.put(column, y, '─', fg, bg);
+            self.put(column, bottom, '─', fg, bg);
+        }
+        self.put(x, y, '┌', fg, bg);
+        self.put(right, y, '┐', fg, bg);
+        self.put(x, bottom, '└', fg, bg);
+        self.put(right, bottom, '┘', fg, bg);
+        // "┌─ title ─┐" needs 6 columns around the title, which is cut to fit
+        if !title.is_empty() && width > 6 {
+            let title: String = title.chars().take(width - 6).collect();
+            self.text(x + 2, y, &format!(" {} ", title), width - 4, fg, bg);
+        }
+    }
+
+    // Rows with a color escape wherever the colors change and a reset at the end of each.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        for row in self.cells.chunks(self.width.max(1)).take(self.height) {
+            let mut current = None;
+            for cell in row {
+                if current != Some((cell.fg, cell.bg)) {
+                    let (fg, bg) = (cell.fg, cell.bg);
+                    out += &format!(
+                        "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                        fg.r, fg.g, fg.b, bg.r, bg.g, bg.b
+                    );
+                    current = Some((fg, bg));
+                }
+                out.push(cell.ch);
+            }
+            out += "\x1b[0m\n";
+        }
+        out
+    }
+}
+
+impl fmt::Display for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.cells.chunks(self.width.max(1)).take(self.height) {
+            let line: String = row.iter().map(|cell| cell.ch).collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Window {
+    // `width` includes the border, the height follows from the widgets.
+    pub fn render(&self, width: usize) -> Canvas {
+        let mut layout = Layout::for_window(self);
+        let (_, height) = layout.preferred_size(self);
+        layout.resize(self, width as i64, height);
+        self.render_layout(&layout)
+    }
+
+    // Draws the widgets where `layout` placed them at its last resize, the window border
+    // goes around the whole layout.
+    pub fn render_layout(&self, layout: &Layout) -> Canvas {
+        let (width, height) = layout.size();
+        let (width, height) = (width.max(0) as usize, height.max(0) as usize);
+        let (fg, bg) = (self.text, self.background);
+        let mut canvas = Canvas::new(width, height, fg, bg);
+        canvas.frame(0, 0, (width, height), &self.widget_name, fg, bg);
+        for (index, widget) in self.widgets.iter().enumerate() {
+            let Some(rect) = layout.widget_rect(index) else {
+                continue;
+            };
+            let area = Area::from(rect);
+            match widget {
+                Widget::ScrollBar(scroll_bar) => {
+                    draw_scroll_bar(&mut canvas, area, scroll_bar, self)
+                }
+                Widget::Button(button) => {
+                    draw_button(&mut canvas, area.x, area.y, area.width, button);
+                }
+                Widget::Menu(menu) => draw_menu(&mut canvas, area, menu),
+                Widget::TextBox(text_box) => draw_text_box(&mut canvas, area, text_box),
+                Widget::Label(label) => {
+                    canvas.text(area.x, area.y, &label.content, area.width, label.text, bg);
+                }
+                Widget::Dialog(dialog) => draw_dialog(&mut canvas, area, dialog),
+            }
+        }
+        canvas
+    }
+}
+
+// A layout rectangle in canvas cells, the parts off the canvas are cut.
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl From<Rect> for Area {
+    fn from(rect: Rect) -> Self {
+        let (x, y) = (rect.x.max(0), rect.y.max(0));
+        Self {
+            x: x as usize,
+            y: y as usize,
+            width: (rect.x + rect.width - x).max(0) as usize,
+            height: (rect.y + rect.height - y).max(0) as usize,
+        }
+    }
+}
+
+// `[ OK ]` with a flat outline, `[[ OK ]]` with a bevel.
+pub(super) fn button_face(button: &Button) -> String {
+    if button.border_width > 1 {
+        format!("[[ {} ]]", button.label)
+    } else {
+        format!("[ {} ]", button.label)
+    }
+}
+
+// Returns the columns taken.
+fn draw_button(canvas: &mut Canvas, x: usize, y: usize, max: usize, button: &Button) -> usize {
+    canvas.text(
+        x,
+        y,
+        &button_face(button),
+        max,
+        button.text,
+        button.background,
+    )
+}
+
+// The first item is drawn selected.
+fn draw_menu(canvas: &mut Canvas, area: Area, menu: &Menu) {
+    canvas.fill(area.x, area.y, area.width, menu.text, menu.background);
+    let (mut x, right) = (area.x, area.x + area.width);
+    for (index, item) in menu.items.iter().enumerate() {
+        let bg = if index == 0 {
+            menu.highlight
+        } else {
+            menu.background
+        };
+        let item = format!(" {} ", item);
+        x += canvas.text(x, area.y, &item, right.saturating_sub(x), menu.text, bg);
+    }
+}
+
+// The value padded with `_` to the visible width, between brackets when it has a border.
+fn draw_text_box(canvas: &mut Canvas, area: Area, text_box: &TextBox) {
+    let width = text_box.width.max(0) as usize;
+    let field: String = text_box
+        .value
+        .chars()
+        .chain(std::iter::repeat('_'))
+        .take(width)
+        .collect();
+    let field = if text_box.border_width > 0 {
+        format!("[{}]", field)
+    } else {
+        field
+    };
+    canvas.text(
+        area.x,
+        area.y,
+        &field,
+        area.width,
+        text_box.text,
+        text_box.background,
+    );
+}
+
+// Scroll bars have no colors of their own and use the window's. The thumb sits as far
+// along the track as the bar is scrolled.
+fn draw_scroll_bar(canvas: &mut Canvas, area: Area, scroll_bar: &ScrollBar, window: &Window) {
+    if area.width < 2 {
+        return;
+    }
+    let track = area.width - 2;
+    let thumb = (track / 4).max(1).min(track);
+    let before = (track - thumb) * scroll_bar.position.clamp(0, 100) as usize / 100;
+    let bar: String = std::iter::once('◄')
+        .chain(std::iter::repeat_n('░', before))
+        .chain(std::iter::repeat_n('█', thumb))
+        .chain(std::iter::repeat_n('░', track - thumb - before))
+        .chain(std::iter::once('►'))
+        .collect();
+    canvas.text(
+        area.x,
+        area.y,
+        &bar,
+        area.width,
+        window.text,
+        window.background,
+    );
+}
+
+// A framed box with its buttons on one row.
+fn draw_dialog(canvas: &mut Canvas, area: Area, dialog: &Dialog) {
+    canvas.frame(
+        area.x,
+        area.y,
+        (area.width, area.height),
+        &dialog.title,
+        dialog.text,
+        dialog.background,
+    );
+    let right = area.x + area.width.saturating_sub(1);
+    let mut x = area.x + 2;
+    for button in &dialog.buttons {
+        if x >= right {
+            break;
+        }
+        x += draw_button(canvas, x, area.y + 1, right.saturating_sub(x + 1), button) + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::abstract_factory::*;
+    use super::super::{Direction, Layout};
+
+    #[test]
+    fn draws_every_widget_inside_the_window() {
+        let pm = PMWindowWidget {};
+        let mut window = pm.create_window();
+        let mut text_box = pm.create_text_box();
+        text_box.value = "ada".to_string();
+        text_box.width = 8;
+        window
+            .add(pm.create_menu(&["File", "Edit"]))
+            .add(pm.create_label("Name"))
+            .add(text_box)
+            .add(pm.create_button("OK"))
+            .add(pm.create_scroll_bar())
+            .add(pm.create_dialog("Quit?", &["Yes", "No"]));
+        assert_eq!(
+            window.render(26).to_string(),
+            "\
+┌─ PMWindow ─────────────┐
+│  File  Edit            │
+│ Name                   │
+│ [ada_____]             │
+│ [ OK ]                 │
+│ ◄█████░░░░░░░░░░░░░░░► │
+│ ┌─ Quit? ────────────┐ │
+│ │ [ Yes ] [ No ]     │ │
+│ └────────────────────┘ │
+└────────────────────────┘
+"
+        );
+    }
+
+    #[test]
+    fn places_the_scroll_bar_thumb_at_its_position() {
+        let pm = PMWindowWidget {};
+        let mut window = pm.create_window();
+        for position in [0, 50, 100, 250] {
+            let mut scroll_bar = pm.create_scroll_bar();
+            scroll_bar.position = position;
+            window.add(scroll_bar);
+        }
+        assert_eq!(
+            window.render(16).to_string(),
+            "\
+┌─ PMWindow ───┐
+│ ◄██░░░░░░░░► │
+│ ◄░░░░██░░░░► │
+│ ◄░░░░░░░░██► │
+│ ◄░░░░░░░░██► │
+└──────────────┘
+"
+        );
+    }
+
+    #[test]
+    fn draws_widgets_where_the_layout_puts_them() {
+        let pm = PMWindowWidget {};
+        let mut window = pm.create_window();
+        let mut text_box = pm.create_text_box();
+        text_box.value = "ada".to_string();
+        text_box.width = 8;
+        window
+            .add(pm.create_label("Name"))
+            .add(text_box)
+            .add(pm.create_button("OK"));
+        let mut layout = Layout::new(Direction::Row);
+        let root = layout.root();
+        layout.padding(root, 1, 2).spacing(root, 1);
+        for index in 0..3 {
+            layout.widget(root, index).unwrap();
+        }
+        layout.resize(&window, 30, 3);
+        assert_eq!(
+            window.render_layout(&layout).to_string(),
+            "\
+┌─ PMWindow ─────────────────┐
+│ Name [ada_____] [ OK ]     │
+└────────────────────────────┘
+"
+        );
+    }
+
+    #[test]
+    fn clips_to_narrow_windows() {
+        let motif = MotifWidget {};
+        let mut window = motif.create_window();
+        window
+            .add(motif.create_button("Cancel"))
+            .add(motif.create_dialog("Delete everything?", &["Yes", "No"]));
+        assert_eq!(
+            window.render(14).to_string(),
+            "\
+┌─ MotifSco ─┐
+│ [[ Cancel  │
+│ ┌─ Dele ─┐ │
+│ │ [[ Yes │ │
+│ └────────┘ │
+└────────────┘
+"
+        );
+    }
+
+    #[test]
+    fn colors_come_from_the_theme() {
+        let motif = MotifWidget {};
+        let mut window = motif.create_window();
+        window.add(motif.create_button("OK"));
+        let canvas = window.render(12);
+        let button = motif.create_button("OK");
+        let cell = canvas.cell(4, 1).unwrap();
+        assert_eq!(cell.ch, ' ');
+        assert_eq!((cell.fg, cell.bg), (button.text, button.background));
+        assert_eq!(canvas.cell(0, 0).unwrap().bg, window.background);
+        assert!(canvas.cell(12, 0).is_none());
+
+        let ansi = canvas.to_ansi();
+        assert_eq!(ansi.lines().count(), canvas.height());
+        // one escape per change of colors: border, button, padding after it
+        let row = ansi.lines().nth(1).unwrap();
+        assert_eq!(row.matches("\x1b[38;2;").count(), 3);
+        assert!(row.contains("\x1b[38;2;255;255;255;48;2;112;128;144m[[ OK ]]"));
+        assert!(row.ends_with("\x1b[0m"));
+    }
+}
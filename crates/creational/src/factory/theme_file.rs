@@ -0,0 +1,453 @@
+// Themes described in a text file instead of code, so a new look ships without a rebuild.
+// The format is a small TOML subset: `[section]` headers, `key = value` lines with quoted
+// strings or integers, and `#` comments. Colors are strings in any notation `Color` parses.
+//
+//   name = "Solarized"
+//
+//   [window]
+//   background = "#fdf6e3"
+//   text = "#657b83"
+//
+//   [scroll_bar]
+//   width = 16
+//   height = 240
+//   name = "SolarizedScrollBar"   # optional, like window.name
+//
+// Errors carry the 1-based line and column of the offending token. A missing required
+// key points at its section header, or at the end of the file when the section is missing.
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use super::abstract_factory::WidgetFactory;
+use super::{Color, ScrollBar, ThemeInfo, Window};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThemeFileErrorKind {
+    // the line is neither a section header, a `key = value` pair nor a comment
+    Syntax(&'static str),
+    UnterminatedString,
+    UnknownSection(String),
+    UnknownKey(String),
+    DuplicateKey(String),
+    MissingKey(String),
+    // the value has the wrong type or is out of range
+    InvalidValue { key: String, expected: &'static str },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeFileError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ThemeFileErrorKind,
+}
+
+impl fmt::Display for ThemeFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        match &self.kind {
+            ThemeFileErrorKind::Syntax(expected) => write!(f, "expected {}", expected),
+            ThemeFileErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            ThemeFileErrorKind::UnknownSection(section) => {
+                write!(f, "unknown section `[{}]`", section)
+            }
+            ThemeFileErrorKind::UnknownKey(key) => write!(f, "unknown key `{}`", key),
+            ThemeFileErrorKind::DuplicateKey(key) => write!(f, "`{}` is set twice", key),
+            ThemeFileErrorKind::MissingKey(key) => write!(f, "missing required key `{}`", key),
+            ThemeFileErrorKind::InvalidValue { key, expected } => {
+                write!(f, "`{}` must be {}", key, expected)
+            }
+        }
+    }
+}
+
+impl Error for ThemeFileError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Text(String),
+    Integer(i64),
+}
+
+// A value with the position of its key, keys are `section.key` or a bare top-level key.
+#[derive(Debug)]
+struct Entry {
+    value: Value,
+    line: usize,
+    column: usize,
+}
+
+// Keys a theme file may set, the optional ones have a default.
+const SECTIONS: [&str; 2] = ["window", "scroll_bar"];
+const KEYS: [(&str, bool); 7] = [
+    ("name", true),
+    ("window.background", true),
+    ("window.text", true),
+    ("window.name", false),
+    ("scroll_bar.width", true),
+    ("scroll_bar.height", true),
+    ("scroll_bar.name", false),
+];
+
+// A `WidgetFactory` whose products are read from a theme file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemeFile {
+    pub name: String,
+    pub window: Window,
+    pub scroll_bar: ScrollBar,
+}
+
+impl ThemeFile {
+    pub fn parse(source: &str) -> Result<Self, ThemeFileError> {
+        let (entries, sections) = parse_entries(source)?;
+        let end = (source.lines().count() + 1, 1);
+        for (key, required) in KEYS {
+            if required && !entries.contains_key(key) {
+                let section = key.split_once('.').map(|(section, _)| section);
+                let (line, column) = section
+                    .and_then(|section| sections.get(section).copied())
+                    .unwrap_or(end);
+                return Err(ThemeFileError {
+                    line,
+                    column,
+                    kind: ThemeFileErrorKind::MissingKey(key.to_string()),
+                });
+            }
+        }
+        let name = text(&entries, "name")?.unwrap_or_default();
+        let size = |key| match entries.get(key) {
+            Some(Entry {
+                value: Value::Integer(size),
+                ..
+            }) if *size > 0 => Ok(*size),
+            Some(entry) => Err(invalid(entry, key, "a positive integer")),
+            None => Ok(0),
+        };
+        Ok(Self {
+            window: Window {
+                background: color(&entries, "window.background")?,
+                text: color(&entries, "window.text")?,
+                widget_name: text(&entries, "window.name")?
+                    .unwrap_or_else(|| format!("{}Window", name)),
+                theme: name.clone(),
+                widgets: vec![],
+            },
+            scroll_bar: ScrollBar {
+                width: size("scroll_bar.width")?,
+                height: size("scroll_bar.height")?,
+                widget_name: text(&entries, "scroll_bar.name")?
+                    .unwrap_or_else(|| format!("{}ScrollBar", name)),
+                theme: name.clone(),
+                position: 0,
+            },
+            name,
+        })
+    }
+}
+
+impl FromStr for ThemeFile {
+    type Err = ThemeFileError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::parse(source)
+    }
+}
+
+impl WidgetFactory for ThemeFile {
+    fn create_window(&self) -> Window {
+        self.window.clone()
+    }
+    fn create_scroll_bar(&self) -> ScrollBar {
+        self.scroll_bar.clone()
+    }
+    fn get_theme_info(&self) -> ThemeInfo {
+        ThemeInfo {
+            name: self.name.clone(),
+        }
+    }
+}
+
+fn invalid(entry: &Entry, key: &str, expected: &'static str) -> ThemeFileError {
+    ThemeFileError {
+        line: entry.line,
+        column: entry.column,
+        kind: ThemeFileErrorKind::InvalidValue {
+            key: key.to_string(),
+            expected,
+        },
+    }
+}
+
+fn text(entries: &HashMap<String, Entry>, key: &str) -> Result<Option<String>, ThemeFileError> {
+    match entries.get(key) {
+        Some(Entry {
+            value: Value::Text(text),
+            ..
+        }) if !text.trim().is_empty() => Ok(Some(text.clone())),
+        Some(entry) => Err(invalid(entry, key, "a non-empty string")),
+        None => Ok(None),
+    }
+}
+
+// Required, checked before.
+fn color(entries: &HashMap<String, Entry>, key: &str) -> Result<Color, ThemeFileError> {
+    let entry = &entries[key];
+    let color = match &entry.value {
+        Value::Text(text) => text.parse().ok(),
+        Value::Integer(_) => None,
+    };
+    color.ok_or_else(|| invalid(entry, key, "a color name, #rgb, #rrggbb or rgb(r, g, b)"))
+}
+
+type Sections = HashMap<String, (usize, usize)>;
+
+// Every `key = value` line keyed by its full name, and the position of every section header.
+fn parse_entries(source: &str) -> Result<(HashMap<String, Entry>, Sections), ThemeFileError> {
+    let mut entries = HashMap::new();
+    let mut sections = HashMap::new();
+    let mut section: Option<String> = None;
+    for (index, raw) in source.lines().enumerate() {
+        let line = index + 1;
+        let column = |byte: usize| raw[..byte].chars().count() + 1;
+        let error = |byte: usize, kind| ThemeFileError {
+            line,
+            column: column(byte),
+            kind,
+        };
+        let start = raw.len() - raw.trim_start().len();
+        let content = raw.trim();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = content.strip_prefix('[') {
+            let Some((name, rest)) = header.split_once(']') else {
+                return Err(error(raw.len(), ThemeFileErrorKind::Syntax("`]`")));
+            };
+            let rest_start = start + 1 + name.len() + 1;
+            if !(rest.trim().is_empty() || rest.trim_start().starts_with('#')) {
+                let offset = rest_start + rest.len() - rest.trim_start().len();
+                return Err(error(offset, ThemeFileErrorKind::Syntax("end of line")));
+            }
+            let name = name.trim();
+            if !SECTIONS.contains(&name) {
+                return Err(error(
+                    start,
+                    ThemeFileErrorKind::UnknownSection(name.to_string()),
+                ));
+            }
+            if sections
+                .insert(name.to_string(), (line, start + 1))
+                .is_some()
+            {
+                return Err(error(
+                    start,
+                    ThemeFileErrorKind::DuplicateKey(format!("[{}]", name)),
+                ));
+            }
+            section = Some(name.to_string());
+            continue;
+        }
+        let Some((key, value)) = content.split_once('=') else {
+            return Err(error(
+                start + content.len(),
+                ThemeFileErrorKind::Syntax("`=`"),
+            ));
+        };
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(error(start, ThemeFileErrorKind::Syntax("a key")));
+        }
+        let full_key = match &section {
+            Some(section) => format!("{}.{}", section, key),
+            None => key.to_string(),
+        };
+        if !KEYS.iter().any(|(known, _)| *known == full_key) {
+            return Err(error(start, ThemeFileErrorKind::UnknownKey(full_key)));
+        }
+        let value_start = start + content.len() - value.trim_start().len();
+        let value = parse_value(&raw[value_start..])
+            .map_err(|(offset, kind)| error(value_start + offset, kind))?;
+        let entry = Entry {
+            value,
+            line,
+            column: column(value_start),
+        };
+        if entries.insert(full_key.clone(), entry).is_some() {
+            return Err(error(start, ThemeFileErrorKind::DuplicateKey(full_key)));
+        }
+    }
+    Ok((entries, sections))
+}
+
+// A quoted string with `\"` and `\\` escapes or an integer, optionally followed by a
+// comment. Errors carry the byte offset into `text`.
+fn parse_value(text: &str) -> Result<Value, (usize, ThemeFileErrorKind)> {
+    let (value, rest) = if let Some(quoted) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        let end = loop {
+            match chars.next() {
+                Some((i, '"')) => break i,
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, c @ ('"' | '\\'))) => value.push(c),
+                    // `i` counts from after the opening quote
+                    Some((i, _)) => {
+                        return Err((1 + i, ThemeFileErrorKind::Syntax("`\\\"` or `\\\\`")));
+                    }
+                    None => return Err((0, ThemeFileErrorKind::UnterminatedString)),
+                },
+                Some((_, c)) => value.push(c),
+                None => return Err((0, ThemeFileErrorKind::UnterminatedString)),
+            }
+        };
+        (Value::Text(value), 1 + end + 1)
+    } else {
+        let end = text
+            .char_indices()
+            .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && *c == '-')))
+            .map_or(text.len(), |(i, _)| i);
+        match text[..end].parse::<i64>() {
+            Ok(number) => (Value::Integer(number), end),
+            Err(_) => return Err((0, ThemeFileErrorKind::Syntax("a quoted string or integer"))),
+        }
+    };
+    let trailing = &text[rest..];
+    let comment = trailing.trim_start();
+    if !(comment.is_empty() || comment.starts_with('#')) {
+        let offset = rest + trailing.len() - comment.len();
+        return Err((offset, ThemeFileErrorKind::Syntax("end of line")));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::*;
+
+    const SOLARIZED: &str = r##"# a light theme
+name = "Solarized"
+
+[window]
+background = "#fdf6e3"
+text = "#657b83"   # base00
+
+[scroll_bar]
+width = 16
+height = 240
+name = "Solarized \"thin\" bar"
+"##;
+
+    fn error(source: &str) -> (usize, usize, ThemeFileErrorKind) {
+        let error = ThemeFile::parse(source).unwrap_err();
+        (error.line, error.column, error.kind)
+    }
+
+    #[test]
+    fn parses_a_theme_into_a_widget_factory() {
+        let theme: ThemeFile = SOLARIZED.parse().unwrap();
+        let window = theme.create_window();
+        assert_eq!(window.background, Color::hex(0xfdf6e3));
+        assert_eq!(window.text, Color::rgb(0x65, 0x7b, 0x83));
+        assert_eq!(window.widget_name, "SolarizedWindow");
+        let scroll_bar = theme.create_scroll_bar();
+        assert_eq!((scroll_bar.width, scroll_bar.height), (16, 240));
+        assert_eq!(scroll_bar.widget_name, r#"Solarized "thin" bar"#);
+        assert_eq!(theme.get_theme_info().name, "Solarized");
+
+        let mut registry = ThemeRegistry::new();
+        registry.load(SOLARIZED).unwrap();
+        assert_eq!(registry.themes(), ["Motif", "PMWindow", "Solarized"]);
+    }
+
+    #[test]
+    fn reports_malformed_lines_with_their_position() {
+        assert_eq!(
+            error("name = \"x\"\n[window\n"),
+            (2, 8, ThemeFileErrorKind::Syntax("`]`"))
+        );
+        assert_eq!(
+            error("name \"x\""),
+            (1, 9, ThemeFileErrorKind::Syntax("`=`"))
+        );
+        assert_eq!(
+            error("name = \"x"),
+            (1, 8, ThemeFileErrorKind::UnterminatedString)
+        );
+        // the character after the backslash
+        assert_eq!(
+            error("name = \"a\\qb\""),
+            (1, 11, ThemeFileErrorKind::Syntax("`\\\"` or `\\\\`"))
+        );
+        assert_eq!(
+            error("[window]\n  text = \"#\\x\""),
+            (2, 13, ThemeFileErrorKind::Syntax("`\\\"` or `\\\\`"))
+        );
+        assert_eq!(
+            error("name = \"x\" trailing"),
+            (1, 12, ThemeFileErrorKind::Syntax("end of line"))
+        );
+        assert_eq!(
+            error("name = \"x\"\n  [button]"),
+            (
+                2,
+                3,
+                ThemeFileErrorKind::UnknownSection("button".to_string())
+            )
+        );
+        assert_eq!(
+            error("name = \"x\"\n[window]\ncolor = \"red\""),
+            (
+                3,
+                1,
+                ThemeFileErrorKind::UnknownKey("window.color".to_string())
+            )
+        );
+        let twice = SOLARIZED.replace("height = 240", "width = 20");
+        assert_eq!(
+            error(&twice),
+            (
+                10,
+                1,
+                ThemeFileErrorKind::DuplicateKey("scroll_bar.width".to_string())
+            )
+        );
+        let error = ThemeFile::parse(&SOLARIZED.replace("\"Solarized\"", "1")).unwrap_err();
+        assert_eq!(error.to_string(), "2:8: `name` must be a non-empty string");
+    }
+
+    #[test]
+    fn validates_required_keys_and_values() {
+        let no_text = SOLARIZED.replace("text = \"#657b83\"   # base00\n", "");
+        assert_eq!(
+            error(&no_text),
+            (
+                4,
+                1,
+                ThemeFileErrorKind::MissingKey("window.text".to_string())
+            )
+        );
+        let no_scroll_bar = SOLARIZED.split("[scroll_bar]").next().unwrap();
+        assert_eq!(
+            error(no_scroll_bar),
+            (
+                8,
+                1,
+                ThemeFileErrorKind::MissingKey("scroll_bar.width".to_string())
+            )
+        );
+        let negative = SOLARIZED.replace("width = 16", "width = -16");
+        assert_eq!(
+            error(&negative),
+            (
+                9,
+                9,
+                ThemeFileErrorKind::InvalidValue {
+                    key: "scroll_bar.width".to_string(),
+                    expected: "a positive integer",
+                }
+            )
+        );
+    }
+}
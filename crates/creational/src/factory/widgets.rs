@@ -0,0 +1,235 @@
+// The rest of the widget family next to `Window` and `ScrollBar`. Every widget records the
+// theme that created it, so a window assembled from several factories can be caught:
+//
+//   let mut window = pm.create_window();
+//   window.add(pm.create_button("OK")).add(motif.create_label("Name"));
+//   window.check_theme()  --> Err(ThemeMismatch { .. "MotifLabel" .. })
+use std::error::Error;
+use std::fmt;
+
+use super::{Color, ScrollBar, Window};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Button {
+    pub label: String,
+    pub background: Color,
+    pub text: Color,
+    // 1 is a flat outline, 2 and more a bevel
+    pub border_width: i64,
+    pub widget_name: String,
+    pub theme: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Menu {
+    pub items: Vec<String>,
+    pub background: Color,
+    pub text: Color,
+    // background of the selected item
+    pub highlight: Color,
+    pub widget_name: String,
+    pub theme: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextBox {
+    pub value: String,
+    pub background: Color,
+    pub text: Color,
+    pub border_width: i64,
+    // visible characters
+    pub width: i64,
+    pub widget_name: String,
+    pub theme: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Label {
+    pub content: String,
+    pub text: Color,
+    pub widget_name: String,
+    pub theme: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dialog {
+    pub title: String,
+    pub background: Color,
+    pub text: Color,
+    pub buttons: Vec<Button>,
+    pub widget_name: String,
+    pub theme: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Widget {
+    ScrollBar(ScrollBar),
+    Button(Button),
+    Menu(Menu),
+    TextBox(TextBox),
+    Label(Label),
+    Dialog(Dialog),
+}
+
+impl Widget {
+    pub fn widget_name(&self) -> &str {
+        match self {
+            Widget::ScrollBar(widget) => &widget.widget_name,
+            Widget::Button(widget) => &widget.widget_name,
+            Widget::Menu(widget) => &widget.widget_name,
+            Widget::TextBox(widget) => &widget.widget_name,
+            Widget::Label(widget) => &widget.widget_name,
+            Widget::Dialog(widget) => &widget.widget_name,
+        }
+    }
+    pub fn theme(&self) -> &str {
+        match self {
+            Widget::ScrollBar(widget) => &widget.theme,
+            Widget::Button(widget) => &widget.theme,
+            Widget::Menu(widget) => &widget.theme,
+            Widget::TextBox(widget) => &widget.theme,
+            Widget::Label(widget) => &widget.theme,
+            Widget::Dialog(widget) => &widget.theme,
+        }
+    }
+}
+
+macro_rules! into_widget {
+    ($($widget:ident),*) => {
+        $(impl From<$widget> for Widget {
+            fn from(widget: $widget) -> Self {
+                Widget::$widget(widget)
+            }
+        })*
+    };
+}
+into_widget!(ScrollBar, Button, Menu, TextBox, Label, Dialog);
+
+// A widget of a window created by another theme than the window itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeMismatch {
+    pub window_theme: String,
+    pub widget_name: String,
+    pub widget_theme: String,
+}
+
+impl fmt::Display for ThemeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` comes from theme `{}` but its window from `{}`",
+            self.widget_name, self.widget_theme, self.window_theme
+        )
+    }
+}
+
+impl Error for ThemeMismatch {}
+
+impl Window {
+    pub fn add(&mut self, widget: impl Into<Widget>) -> &mut Self {
+        self.widgets.push(widget.into());
+        self
+    }
+
+    // Every widget, including the buttons of a dialog, must come from the window's theme.
+    pub fn check_theme(&self) -> Result<(), ThemeMismatch> {
+        let dialog_buttons = self.widgets.iter().flat_map(|widget| match widget {
+            Widget::Dialog(dialog) => dialog.buttons.as_slice(),
+            _ => &[],
+        });
+        let themes = self
+            .widgets
+            .iter()
+            .map(|widget| (widget.widget_name(), widget.theme()))
+            .chain(
+                dialog_buttons.map(|button| (button.widget_name.as_str(), button.theme.as_str())),
+            );
+        for (widget_name, theme) in themes {
+            if theme != self.theme {
+                return Err(ThemeMismatch {
+                    window_theme: self.theme.clone(),
+                    widget_name: widget_name.to_string(),
+                    widget_theme: theme.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::abstract_factory::*;
+    use super::super::*;
+
+    #[test]
+    fn each_theme_styles_its_widgets() {
+        let (pm, motif) = (PMWindowWidget {}, MotifWidget {});
+        let pm_button = pm.create_button("OK");
+        let motif_button = motif.create_button("OK");
+        assert_eq!(pm_button.label, "OK");
+        assert_eq!(pm_button.border_width, 1);
+        assert_eq!(motif_button.border_width, 2);
+        assert_ne!(pm_button.background, motif_button.background);
+        assert_eq!(motif_button.widget_name, "MotifButton");
+
+        let menu = pm.create_menu(&["Open", "Save"]);
+        assert_eq!(menu.items, ["Open", "Save"]);
+        assert_eq!(menu.theme, "PMWindow Widget");
+        assert_eq!(motif.create_text_box().width, 24);
+        assert_eq!(motif.create_label("Name").text, Color::WHITE);
+
+        let dialog = motif.create_dialog("Quit?", &["Yes", "No"]);
+        assert_eq!(dialog.title, "Quit?");
+        let labels: Vec<&str> = dialog.buttons.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(labels, ["Yes", "No"]);
+        assert!(dialog
+            .buttons
+            .iter()
+            .all(|button| button.theme == dialog.theme));
+    }
+
+    #[test]
+    fn custom_factories_get_widgets_in_their_window_colors() {
+        let theme: ThemeFile = "name = \"Paper\"\n[window]\nbackground = \"white\"\n\
+            text = \"black\"\n[scroll_bar]\nwidth = 1\nheight = 10\n"
+            .parse()
+            .unwrap();
+        let button = theme.create_button("OK");
+        assert_eq!(
+            (button.background, button.text),
+            (Color::BLACK, Color::WHITE)
+        );
+        assert_eq!(button.widget_name, "PaperButton");
+        assert_eq!(theme.create_label("x").text, Color::BLACK);
+        let mut window = theme.create_window();
+        window
+            .add(theme.create_scroll_bar())
+            .add(theme.create_dialog("Save?", &["OK"]));
+        assert_eq!(window.check_theme(), Ok(()));
+    }
+
+    #[test]
+    fn reports_widgets_from_another_theme() {
+        let (pm, motif) = (PMWindowWidget {}, MotifWidget {});
+        let mut window = pm.create_window();
+        window
+            .add(pm.create_menu(&["File"]))
+            .add(pm.create_text_box())
+            .add(pm.create_scroll_bar());
+        assert_eq!(window.check_theme(), Ok(()));
+
+        window.add(motif.create_label("Name"));
+        let mismatch = window.check_theme().unwrap_err();
+        assert_eq!(
+            mismatch.to_string(),
+            "`MotifLabel` comes from theme `Motif Widget` but its window from `PMWindow Widget`"
+        );
+
+        let mut window = pm.create_window();
+        let mut dialog = pm.create_dialog("Delete?", &["Cancel"]);
+        dialog.buttons.push(motif.create_button("Delete"));
+        window.add(dialog);
+        assert_eq!(window.check_theme().unwrap_err().widget_name, "MotifButton");
+    }
+}
@@ -1,7 +1,9 @@
 use creational::{builder, factory};
 
 fn demo_factory() {
-    let client = factory::abstract_factory::get_widget_factory(factory::Themes::Motif);
+    let themes = factory::ThemeRegistry::new();
+    println!("Themes::{:?}", themes.themes());
+    let client = themes.get_or_default("Motif");
     let window = client.create_window();
     let scroll = client.create_scroll_bar();
     let info = client.get_theme_info();
@@ -10,7 +12,7 @@ fn demo_factory() {
     println!("Client::ThemeInfo {:?}", info);
 
     println!("----------------------");
-    let client = factory::abstract_factory::get_widget_factory(factory::Themes::PMWindow);
+    let client = themes.get_or_default("PMWindow");
     let window = client.create_window();
     let scroll = client.create_scroll_bar();
     println!("Client::Window {:?}", window);
@@ -23,15 +25,58 @@ fn demo_builder() {
         .limit(10)
         .offset(5)
         .order_by("created_at DESC")
-        .build();
+        .build()
+        .unwrap();
     println!("Your Query::{}", query_builder);
 
     let query_builder = builder::QueryBuilder::new("user")
         .select("id,username,display_name")
         .limit(0)
-        .build();
+        .build()
+        .unwrap();
 
     println!("Your Query::{}", query_builder);
+
+    let query = builder::QueryBuilder::new("user")
+        .select("id,username")
+        .filter(
+            builder::col("age")
+                .gt(18)
+                .and(builder::col("username").like("a%")),
+        )
+        .build()
+        .unwrap();
+    println!("Your Query::{} {:?}", query, query.params);
+
+    let query = builder::QueryBuilder::new("user")
+        .select("id,username")
+        .filter(builder::col("id").is_in([1, 2, 3]))
+        .limit(10)
+        .offset(20)
+        .build_with(&builder::MySQL)
+        .unwrap();
+    println!("Your MySQL Query::{} {:?}", query, query.params);
+
+    let query = builder::InsertBuilder::new("user")
+        .columns("username,display_name")
+        .values(["kevin".into(), "Kevin".into()])
+        .on_conflict("username")
+        .do_update("display_name")
+        .returning("id")
+        .build()
+        .unwrap();
+    println!("Your Query::{} {:?}", query, query.params);
+
+    use builder::{users, TypedColumn};
+    let query = builder::UserQueryBuilder::new()
+        .select(users::id)
+        .select(users::username)
+        .filter(users::username.like("k%"))
+        .order_by(users::created_at.desc())
+        .limit(10)
+        .build()
+        .unwrap();
+    println!("Your Query::{} {:?}", query, query.params);
 }
 fn main() {
     demo_builder();